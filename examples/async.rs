@@ -0,0 +1,63 @@
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake};
+use std::thread::{self, Thread};
+
+use trident::{Backend, Computer, InstructionChain, Measurements, Program};
+
+// A minimal executor, which parks the thread until the future is woken.
+struct Unparker(Thread);
+
+impl Wake for Unparker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Arc::new(Unparker(thread::current())).into();
+    let mut context = Context::from_waker(&waker);
+
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+// A program of 20 qbits copying the 2 first ones with CNOTs in a pattern given by `depth`, whose
+// results always have 2 bits of entropy.
+fn program(computer: &Computer, depth: usize) -> Program {
+    let mut program = computer.new_program(&format!("|{}>", "0".repeat(20)));
+    program.apply_iter("H", 0..2, None);
+    for qbit in 2..20 {
+        program.apply("X", qbit, (qbit as usize * depth / (depth + 1)) as u8);
+    }
+    program.measure(100000)
+}
+
+fn main() {
+    let mut computer = Computer::new(20)
+        .add_default_gates()
+        .backend(Backend::Sparse)
+        .build();
+
+    let mut previous: Option<Measurements> = None;
+
+    for depth in 0..4 {
+        let program = program(&computer, depth);
+        let run = computer.run_async(program, depth as u64);
+
+        // The results of the previous run are processed while the next one runs.
+        if let Some(results) = previous.take() {
+            println!("Entropy of the previous run: {:.3} bits", results.entropy());
+        }
+
+        let results = block_on(run);
+        println!("Run #{} done in {} ms", depth, results.duration().as_millis());
+        previous = Some(results);
+    }
+}
@@ -0,0 +1,45 @@
+use std::time::Instant;
+
+use trident::{Computer, InstructionChain, Sampling};
+
+// Measures the throughput of the samplers of the StateVector backend, on a small circuit sampled
+// many times and on a larger one.
+fn main() {
+    let samples = 1_000_000;
+
+    for &size in &[4, 16] {
+        println!("{} qbits, {} samples:", size, samples);
+
+        for &(sampling, block) in &[
+            (Sampling::Tree, 1024),
+            (Sampling::Tree, 1 << 16),
+            (Sampling::Tree, 1 << 20),
+            (Sampling::AliasTable, 1024),
+        ] {
+            let mut computer = Computer::new(size)
+                .add_default_gates()
+                .sampling(sampling)
+                .measurement_block(block)
+                .build();
+
+            let program = computer.new_program(&*format!("|{}>", "0".repeat(size as usize)))
+                .apply_iter("H", 0..size, None)
+                .measure(samples);
+
+            // The gates are run once, only the sampling is timed
+            let mut prepared = computer.prepare(&program);
+
+            let start = Instant::now();
+            prepared.sample(samples, 0);
+            let seconds = start.elapsed().as_secs_f64();
+
+            println!(
+                "  {:?} with blocks of {:>7} samples: {:>8.2} ms, {:>6.2} M samples/s",
+                sampling,
+                block,
+                seconds * 1000.0,
+                samples as f64 / seconds / 1e6,
+            );
+        }
+    }
+}
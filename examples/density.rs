@@ -0,0 +1,29 @@
+use trident::{Backend, Computer, InstructionChain};
+
+fn main() {
+    // Creates a new computer with 2 qbits, whose state is a density matrix.
+    let mut computer = Computer::new(2)
+        .add_default_gates()
+        .backend(Backend::DensityMatrix)
+        .build();
+
+    println!("{}\n", computer);
+
+    // Prepares the Bell state (|00> + |11>)/sqrt(2).
+    let program = computer.new_program("|00>")
+        .apply("H", 0, None)
+        .apply("X", 1, 0)
+        .measure(1024);
+
+    // The whole state is pure, but each of it's qbits is maximally mixed.
+    let rho = computer.density_matrix(&program, None);
+    println!("{}\n", rho);
+
+    let reduced = rho.partial_trace(&[1]);
+    println!("Purity of qbit #0: {:.3}", reduced.purity());
+    println!("Entropy of qbit #0: {:.3} bits\n", reduced.von_neumann_entropy());
+
+    // Measurements are sampled from the diagonal of the density matrix.
+    let results = computer.run(program, None);
+    println!("{}", results);
+}
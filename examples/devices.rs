@@ -0,0 +1,26 @@
+use trident::{Computer, DeviceKind};
+
+fn main() {
+    // Lists the OpenCL devices of the machine.
+    let devices = Computer::available_devices();
+    for device in devices.iter() {
+        println!("{}", device);
+    }
+    if devices.is_empty() {
+        println!("No OpenCL device available");
+        return;
+    }
+
+    // Selects the first GPU if there is one, otherwise the first device.
+    let kind = match devices.iter().find(|device| device.kind == DeviceKind::Gpu) {
+        Some(_) => DeviceKind::Gpu,
+        None => devices[0].kind,
+    };
+
+    let computer = Computer::new(10)
+        .add_default_gates()
+        .device(kind)
+        .build();
+
+    println!("\n{}", computer);
+}
@@ -0,0 +1,35 @@
+use trident::{c64, Computer, InstructionChain};
+
+fn main() {
+    // Creates a new computer with 2 qbits.
+    let mut computer = Computer::new(2)
+        .add_default_gates()
+        .build();
+
+    println!("{}\n", computer);
+
+    // Starts from a superposition given as a sum of kets, the coefficients being normalized.
+    let program = computer.new_program("0.6|00> + 0.8i|11>")
+        .apply("H", 0, None)
+        .measure(5000);
+
+    let state = computer.state_vector(&program);
+    println!("{}\n", state);
+
+    // The resulting state can be used as the initial state of another program.
+    let program = computer.new_program(&state)
+        .apply("H", 0, None)
+        .measure(5000);
+
+    let results = computer.run(program, None);
+    println!("{}\n", results);
+
+    // Starts from the amplitudes of every basis state, the bit #i of the index being the state of
+    // qbit #i.
+    let amplitudes = [c64::new(0.5, 0.0), c64::new(0.0, 0.5), c64::new(-0.5, 0.0), c64::new(0.0, -0.5)];
+    let program = computer.new_program(&amplitudes[..])
+        .measure(5000);
+
+    let results = computer.run(program, None);
+    println!("{}", results);
+}
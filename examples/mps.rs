@@ -0,0 +1,35 @@
+use trident::{Backend, BitString, Computer, InstructionChain};
+
+fn main() {
+    // Creates a new computer with 80 qbits, whose state is a matrix product state with bonds
+    // of dimension at most 16.
+    let mut computer = Computer::new(80)
+        .add_default_gates()
+        .backend(Backend::MatrixProductState)
+        .max_bond_dimension(16)
+        .build();
+
+    println!("{}\n", computer);
+
+    // Prepares a 80 qbits GHZ state with a chain of CNOTs, the bonds stay of dimension 2.
+    let mut program = computer.new_program(&format!("|{}>", "0".repeat(80)));
+    program.apply("H", 0, None);
+    for qbit in 1..80 {
+        program.apply("X", qbit, qbit - 1);
+    }
+    let program = program.measure(5000);
+
+    let mps = computer.matrix_product_state(&program);
+    println!("{}\n", mps);
+
+    let zeros = BitString::zeros(80);
+    let mut ones = BitString::zeros(80);
+    for qbit in 0..80 {
+        ones.set(qbit, true);
+    }
+    println!("<{}|ψ> = {:?}", zeros, mps.amplitude(&zeros));
+    println!("<{}|ψ> = {:?}\n", ones, mps.amplitude(&ones));
+
+    let results = computer.run(program, None);
+    println!("{}", results);
+}
@@ -0,0 +1,27 @@
+use trident::{Channel, Computer, InstructionChain, NoiseModel};
+
+fn main() {
+    // Every gate depolarizes the qbits it acts on, qbit #1 slowly decays
+    // towards |0> and qbit #0 is sometimes misread.
+    let mut noise = NoiseModel::new();
+    noise.channel(Channel::depolarizing(0.02))
+        .qbit_channel(1, Channel::amplitude_damping(0.05))
+        .readout_error(0, 0.01, 0.03)
+        .trajectories(200);
+
+    // Creates a new noisy computer with 2 qbits.
+    let mut computer = Computer::new(2)
+        .add_default_gates()
+        .noise_model(&noise)
+        .build();
+
+    // Prepares the Bell state (|00> + |11>)/sqrt(2): the noise makes
+    // |01> and |10> appear.
+    let program = computer.new_program("|00>")
+        .apply("H", 0, None)
+        .apply("X", 1, 0)
+        .measure(10000);
+
+    let results = computer.run(program, None);
+    println!("{}", results);
+}
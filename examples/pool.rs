@@ -0,0 +1,54 @@
+use std::thread;
+
+use trident::{Backend, Computer, ComputerPool, InstructionChain};
+
+fn main() {
+    // A pool of 2 computers, which could be on different devices.
+    let computers = (0..2).map(|_| {
+        Computer::new(16)
+            .add_default_gates()
+            .backend(Backend::Sparse)
+            .build()
+    });
+    let pool = ComputerPool::new(computers);
+
+    // Programs are submitted from 4 threads sharing the pool, each one waiting for it's results.
+    thread::scope(|scope| {
+        for client in 0..4 {
+            let pool = &pool;
+
+            scope.spawn(move || {
+                let program = pool.build_program(|computer| {
+                    let mut program = computer.new_program(&format!("|{}>", "0".repeat(16)));
+                    program.apply_iter("H", 0..client + 1, None);
+                    program.measure(10000)
+                });
+
+                let job = pool.submit(program, client as u64);
+                let results = job.wait().expect("The job was not cancelled");
+                println!("Client #{}: {} states measured", client, results.n_most(usize::MAX).len());
+            });
+        }
+    });
+
+    // Queued jobs can be cancelled, but not the running ones.
+    let jobs: Vec<_> = (0..8)
+        .map(|_| {
+            let program = pool.build_program(|computer| {
+                computer.new_program(&format!("|{}>", "0".repeat(16)))
+                    .apply("H", 0, None)
+                    .measure(100000)
+            });
+            pool.submit(program, None)
+        })
+        .collect();
+
+    let last = jobs.last().unwrap();
+    println!("\nJob #{} is {:?}, cancelled: {}", last.id(), last.status(), last.cancel());
+    println!("Other jobs cancelled: {}", pool.cancel_all());
+
+    for job in jobs {
+        let id = job.id();
+        println!("Job #{}: {}", id, if job.wait().is_some() { "done" } else { "cancelled" });
+    }
+}
@@ -0,0 +1,32 @@
+use trident::{BitString, Computer, InstructionChain};
+
+fn main() {
+    // Creates a new computer with 3 qbits.
+    let mut computer = Computer::new(3)
+        .add_default_gates()
+        .build();
+
+    // Prepares a GHZ state, keeping the outcome of the first shots.
+    let program = computer.new_program("|000>")
+        .apply("H", 0, None)
+        .apply("X", 1, 0)
+        .apply("X", 2, 1)
+        .keep_shots(10)
+        .measure(1000);
+
+    // The gates are run once, the state can then be sampled many times.
+    let mut prepared = computer.prepare(&program);
+    println!("State prepared in {} µs\n", prepared.duration().as_micros());
+
+    for seed in 0..3 {
+        let results = prepared.sample(1000, seed);
+        println!("{}", results);
+        println!("First shots: {:?}\n", results.integer_shots(0..3, None));
+    }
+
+    // Exact probabilities of the measured states, bit #i being the state of qbit #i.
+    for value in 0..8 {
+        let state = BitString::from_u64(value, 3);
+        println!("P({}) = {:.3}", value, prepared.probability(&state));
+    }
+}
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use trident::{Backend, BitString, Computer, InstructionChain};
+
+fn main() {
+    let qbits = 6;
+    let samples = 10000;
+    let runs = 200;
+
+    // Every qbit in superposition, the measured states are uniformly distributed.
+    let mut computer = Computer::new(qbits)
+        .add_default_gates()
+        .backend(Backend::Sparse)
+        .build();
+
+    let mut program = computer.new_program(&format!("|{}>", "0".repeat(qbits as usize)));
+    program.apply_iter("H", 0..qbits, None);
+    let program = program.keep_shots(None).measure(samples);
+
+    let uniform: HashMap<BitString, f64> = (0..1 << qbits)
+        .map(|value| (BitString::from_u64(value, qbits as usize), 1.0 / (1 << qbits) as f64))
+        .collect();
+
+    // Goodness of fit of the runs of consecutive seeds: about 1% of them should be rejected at the
+    // 1% level, and the p-values should be uniformly distributed.
+    let mut prepared = computer.prepare(&program);
+    let mut rejected = 0;
+    let mut deciles = [0; 10];
+    for seed in 0..runs {
+        let results = prepared.sample(samples, seed);
+        let test = results.chi_squared(&uniform);
+        if test.p_value < 0.01 {
+            rejected += 1;
+        }
+        deciles[std::cmp::min((test.p_value * 10.0) as usize, 9)] += 1;
+    }
+    println!("Rejected at the 1% level: {} of {} runs", rejected, runs);
+    println!("Deciles of the p-values: {:?}\n", deciles);
+
+    // Independence of consecutive shots: the pairs of the 2 first qbits of shots #i and #i + 1
+    // are uniformly distributed among the 16 pairs.
+    let results = prepared.sample(samples, 42);
+    let shots = results.integer_shots(0..2, None);
+    let mut pairs = [0usize; 16];
+    for pair in shots.windows(2) {
+        pairs[(pair[0] << 2 | pair[1]) as usize] += 1;
+    }
+    let expected = (shots.len() - 1) as f64 / 16.0;
+    let statistic: f64 = pairs.iter()
+        .map(|&observed| (observed as f64 - expected) * (observed as f64 - expected) / expected)
+        .sum();
+    // 37.70 is the 0.1% critical value of the chi-squared distribution with 15 degrees of freedom
+    println!("Pairs of consecutive shots: χ² = {:.2}, rejected: {}\n", statistic, statistic > 37.70);
+
+    // The same seed always gives the same shots.
+    let again = prepared.sample(samples, 42);
+    println!("Reproducible: {}", results.integer_shots(0..qbits, None) == again.integer_shots(0..qbits, None));
+}
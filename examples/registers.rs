@@ -0,0 +1,40 @@
+use trident::{BitOrder, Computer, InstructionChain};
+
+fn main() {
+    // Creates a new computer with 8 qbits.
+    let mut computer = Computer::new(8)
+        .add_default_gates()
+        .build();
+
+    println!("{}\n", computer);
+
+    let mut program = computer.new_program("|00000000>");
+
+    // Splits the qbits into an input and an output register of 4 qbits each.
+    let input = program.register("in", 4);
+    let output = program.register("out", 4);
+
+    // Puts the input in superposition and copies it into the output, except for it's last qbit
+    // which is flipped.
+    program.apply_iter("H", &input, None);
+    for i in 0..3 {
+        program.apply("X", output[i], input[i]);
+    }
+    program.apply("X", output[3], None);
+
+    let program = program.measure(5000);
+    println!("{}\n", program);
+
+    let results = computer.run(program, None);
+
+    // Decodes the registers of every result as integers, their first qbit being the least
+    // significant bit.
+    for (register, histogram) in results.register_histograms(BitOrder::LittleEndian).iter() {
+        println!("{}: {:?}", register, histogram);
+    }
+
+    // The output without it's flipped qbit, read with it's first qbit as the most significant bit,
+    // and the marginal distribution of the first qbits of both registers.
+    println!("{:?}", results.integer_counts(output.iter().take(3), BitOrder::BigEndian));
+    println!("{:?}", results.marginal([input[0], output[0]]));
+}
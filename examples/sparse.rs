@@ -0,0 +1,24 @@
+use trident::{Backend, Computer, InstructionChain};
+
+fn main() {
+    // Creates a new computer with 64 qbits, whose state only stores the nonzero amplitudes.
+    let mut computer = Computer::new(64)
+        .add_default_gates()
+        .backend(Backend::Sparse)
+        .max_support(1024)
+        .build();
+
+    println!("{}\n", computer);
+
+    // Puts the first 4 qbits in superposition and copies them 15 times with CNOTs, the state
+    // has only 16 nonzero amplitudes.
+    let mut program = computer.new_program(&format!("|{}>", "0".repeat(64)));
+    program.apply_iter("H", 0..4, None);
+    for qbit in 4..64 {
+        program.apply("X", qbit, qbit % 4);
+    }
+    let program = program.measure(5000);
+
+    let results = computer.run(program, None);
+    println!("{}", results);
+}
@@ -0,0 +1,29 @@
+use std::f32::consts::FRAC_PI_2;
+
+use trident::{Backend, Computer, Gate, InstructionChain};
+
+fn main() {
+    // Creates a new computer with 100 qbits, way too many for a state vector, simulated
+    // with a stabilizer tableau. Only Clifford gates may be used.
+    let mut computer = Computer::new(100)
+        .add_default_gates()
+        .add_gate("S", Gate::phase_shift(FRAC_PI_2))
+        .backend(Backend::Stabilizer)
+        .build();
+
+    println!("{}\n", computer);
+
+    // Prepares a 100 qbits GHZ state.
+    let program = computer.new_program(&format!("|{}>", "0".repeat(100)))
+        .apply("H", 0, None)
+        .apply("S", 0, None)
+        .apply("S", 0, None)
+        .apply_iter("X", 1..100, 0)
+        .measure(5000);
+
+    assert!(computer.is_clifford(&program));
+
+    // Only |00...0> and |11...1> are measured.
+    let results = computer.run(program, None);
+    println!("{}", results);
+}
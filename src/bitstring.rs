@@ -0,0 +1,365 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+//#################################################################################################
+//
+//                                        Bit string
+//
+//#################################################################################################
+
+// The bits of a bit string: a single word for strings of up to 64 bits, which are the most
+// common, several words otherwise. The bits beyond the length are always 0.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Bits {
+    Small(u64),
+    Large(Box<[u64]>),
+}
+
+/// A string of bits of fixed length, such as a basis state of the register or a measured state:
+/// the bit #i is the state of qbit #i.
+///
+/// Strings of up to 64 bits are stored inline in a single word.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct BitString {
+    len: usize,
+    bits: Bits,
+}
+
+impl BitString {
+    /// Creates a new bit string of `len` bits, all of them set to 0.
+    pub fn zeros(len: usize) -> BitString {
+        let bits = if len <= 64 {
+            Bits::Small(0)
+        } else {
+            Bits::Large(vec![0; len.div_ceil(64)].into())
+        };
+
+        BitString {
+            len,
+            bits,
+        }
+    }
+
+    /// Creates a new bit string of `len` bits from the bits of `value`, bit #i of `value` becoming
+    /// bit #i of the string.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `value` has bits set beyond the `len` first ones.
+    pub fn from_u64(value: u64, len: usize) -> BitString {
+        assert!(
+            len >= 64 || value >> len == 0,
+            "The value {} does not fit in {} bits",
+            value,
+            len,
+        );
+
+        let mut result = BitString::zeros(len);
+        match &mut result.bits {
+            Bits::Small(bits) => *bits = value,
+            Bits::Large(words) => words[0] = value,
+        }
+        result
+    }
+
+    /// Returns the number of bits of the string.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the string has no bits.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the bit #i.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `i` is out of the string.
+    #[inline]
+    pub fn get(&self, i: usize) -> bool {
+        self.check_index(i);
+
+        match &self.bits {
+            Bits::Small(bits) => bits >> i & 1 == 1,
+            Bits::Large(words) => words[i / 64] >> (i % 64) & 1 == 1,
+        }
+    }
+
+    /// Sets the bit #i to `value`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `i` is out of the string.
+    #[inline]
+    pub fn set(&mut self, i: usize, value: bool) {
+        self.check_index(i);
+
+        let (word, mask) = match &mut self.bits {
+            Bits::Small(bits) => (bits, 1 << i),
+            Bits::Large(words) => (&mut words[i / 64], 1 << (i % 64)),
+        };
+
+        if value {
+            *word |= mask;
+        } else {
+            *word &= !mask;
+        }
+    }
+
+    /// Flips the bit #i.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `i` is out of the string.
+    #[inline]
+    pub fn flip(&mut self, i: usize) {
+        let value = self.get(i);
+        self.set(i, !value);
+    }
+
+    /// Returns the number of bits set to 1.
+    pub fn count_ones(&self) -> usize {
+        self.words()
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns the string as an integer, bit #i of the string being bit #i of the result, or `None`
+    /// if it is longer than 64 bits.
+    pub fn to_u64(&self) -> Option<u64> {
+        match self.bits {
+            Bits::Small(bits) => Some(bits),
+            Bits::Large(_) => None,
+        }
+    }
+
+    // Returns the string made of the bits at the given positions, bit #i of the result being the
+    // bit #bits[i] of the string.
+    pub(crate) fn select(&self, bits: &[usize]) -> BitString {
+        let mut result = BitString::zeros(bits.len());
+        for (i, &bit) in bits.iter().enumerate() {
+            if self.get(bit) {
+                result.set(i, true);
+            }
+        }
+        result
+    }
+
+    /// Returns an object formatting the string with it's bits in the given `order`: with
+    /// `BitOrder::BigEndian`, bit #0 is written first, and with `BitOrder::LittleEndian`, last.
+    pub fn display(&self, order: BitOrder) -> impl fmt::Display + '_ {
+        Ordered {
+            bits: self,
+            order,
+        }
+    }
+
+    // Creates a bit string of `len` bits from the words holding it's bits, least significant first,
+    // the bits beyond `len` being 0.
+    pub(crate) fn from_words(words: &[u64], len: usize) -> BitString {
+        let mut result = BitString::zeros(len);
+        match &mut result.bits {
+            Bits::Small(bits) => *bits = words[0],
+            Bits::Large(bits) => bits.copy_from_slice(words),
+        }
+        result
+    }
+
+    // Returns the words holding the bits, least significant first.
+    #[inline]
+    pub(crate) fn words(&self) -> &[u64] {
+        match &self.bits {
+            Bits::Small(bits) => std::slice::from_ref(bits),
+            Bits::Large(words) => words,
+        }
+    }
+
+    #[inline]
+    fn check_index(&self, i: usize) {
+        assert!(
+            i < self.len,
+            "Bit #{} is out of the {}-bits string",
+            i,
+            self.len,
+        );
+    }
+}
+
+// Shorter strings first, then by value.
+impl Ord for BitString {
+    fn cmp(&self, other: &BitString) -> Ordering {
+        self.len.cmp(&other.len)
+            .then_with(|| self.words().iter().rev().cmp(other.words().iter().rev()))
+    }
+}
+
+impl PartialOrd for BitString {
+    fn partial_cmp(&self, other: &BitString) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Formats the string with it's most significant bit first, like a binary integer.
+impl fmt::Display for BitString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.bits {
+            Bits::Small(bits) if self.len != 0 => write!(f, "{:0len$b}", bits, len = self.len),
+            _ => (0..self.len).rev().try_for_each(|i| write!(f, "{}", self.get(i) as u8)),
+        }
+    }
+}
+
+// A bit string formatted in a given bit order.
+struct Ordered<'a> {
+    bits: &'a BitString,
+    order: BitOrder,
+}
+
+impl fmt::Display for Ordered<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.order {
+            BitOrder::LittleEndian => write!(f, "{}", self.bits),
+            BitOrder::BigEndian => (0..self.bits.len).try_for_each(|i| write!(f, "{}", self.bits.get(i) as u8)),
+        }
+    }
+}
+
+impl fmt::Debug for BitString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+//#################################################################################################
+//
+//                                         Bit order
+//
+//#################################################################################################
+
+/// The order in which the qbits of a group are read as the bits of an integer, and in which the
+/// qbits of a state are written in a ket, the most significant one being written first.
+///
+/// The bit order of a computer is set with `ComputerBuilder::bit_order`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BitOrder {
+    /// The first qbit of the group is the least significant bit: qbit #0 is written last, as in
+    /// `|q2 q1 q0>`.
+    LittleEndian,
+    /// The first qbit of the group is the most significant bit: qbit #0 is written first, as in
+    /// `|q0 q1 q2>`.
+    BigEndian,
+}
+
+impl BitOrder {
+    // Returns the bit written at `position` in a string of `len` digits.
+    #[inline]
+    pub(crate) fn index(self, position: usize, len: usize) -> usize {
+        match self {
+            BitOrder::LittleEndian => len - 1 - position,
+            BitOrder::BigEndian => position,
+        }
+    }
+
+    // Reads the bits of `state` at the positions `bits` as an unsigned integer, in this order.
+    //
+    // Panics if there are more than 64 positions or if one is out of the string.
+    pub(crate) fn decode(self, state: &BitString, bits: &[usize]) -> u64 {
+        assert!(
+            bits.len() <= 64,
+            "Cannot decode {} bits as a 64 bits integer",
+            bits.len(),
+        );
+
+        let len = bits.len();
+        bits.iter()
+            .enumerate()
+            .fold(0, |value, (i, &bit)| {
+                let shift = match self {
+                    BitOrder::LittleEndian => i,
+                    BitOrder::BigEndian => len - 1 - i,
+                };
+                value | (state.get(bit) as u64) << shift
+            })
+    }
+}
+
+//#################################################################################################
+//
+//                                           Tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The `len` bits string whose bits `ones` are set.
+    fn bits(len: usize, ones: &[usize]) -> BitString {
+        let mut result = BitString::zeros(len);
+        ones.iter().for_each(|&i| result.set(i, true));
+        result
+    }
+
+    #[test]
+    fn decode() {
+        let state = BitString::from_u64(0b0011, 4);
+        assert_eq!(BitOrder::LittleEndian.decode(&state, &[0, 1, 2, 3]), 0b0011);
+        assert_eq!(BitOrder::BigEndian.decode(&state, &[0, 1, 2, 3]), 0b1100);
+
+        // The positions are read in the given order
+        let state = BitString::from_u64(0b0100, 4);
+        assert_eq!(BitOrder::LittleEndian.decode(&state, &[2, 0]), 0b01);
+        assert_eq!(BitOrder::BigEndian.decode(&state, &[2, 0]), 0b10);
+        assert_eq!(BitOrder::LittleEndian.decode(&state, &[]), 0);
+    }
+
+    #[test]
+    fn decode_wide() {
+        let state = bits(100, &[70, 99]);
+        let positions: Vec<usize> = (36..100).collect();
+
+        assert_eq!(BitOrder::LittleEndian.decode(&state, &positions), 1 << 34 | 1 << 63);
+        assert_eq!(BitOrder::BigEndian.decode(&state, &positions), 1 << 29 | 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot decode 65 bits")]
+    fn decode_too_wide() {
+        let positions: Vec<usize> = (0..65).collect();
+        BitOrder::LittleEndian.decode(&BitString::zeros(100), &positions);
+    }
+
+    #[test]
+    fn display() {
+        let state = BitString::from_u64(0b0011, 4);
+        assert_eq!(state.to_string(), "0011");
+        assert_eq!(state.display(BitOrder::LittleEndian).to_string(), "0011");
+        assert_eq!(state.display(BitOrder::BigEndian).to_string(), "1100");
+    }
+
+    #[test]
+    fn display_wide() {
+        let state = bits(100, &[0, 70]);
+        let little = state.display(BitOrder::LittleEndian).to_string();
+        let big = state.display(BitOrder::BigEndian).to_string();
+
+        assert_eq!(little.len(), 100);
+        assert_eq!(big, little.chars().rev().collect::<String>());
+        assert_eq!(big.match_indices('1').map(|(i, _)| i).collect::<Vec<_>>(), vec![0, 70]);
+    }
+
+    #[test]
+    fn index() {
+        // The digit written at a position is the bit the string displays there
+        let state = bits(70, &[1, 64, 69]);
+        for &order in [BitOrder::LittleEndian, BitOrder::BigEndian].iter() {
+            let digits: Vec<char> = state.display(order).to_string().chars().collect();
+            for (position, &digit) in digits.iter().enumerate() {
+                assert_eq!(digit == '1', state.get(order.index(position, 70)));
+            }
+        }
+    }
+}
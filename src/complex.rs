@@ -35,6 +35,18 @@ impl c64 {
         c64(r*arg.cos(), r*arg.sin())
     }
 
+    /// Returns the real part of `self`.
+    #[inline]
+    pub fn re(self) -> f32 {
+        self.0
+    }
+
+    /// Returns the imaginary part of `self`.
+    #[inline]
+    pub fn im(self) -> f32 {
+        self.1
+    }
+
     /// Returns the complex conjugate of `self`.
     #[inline]
     pub fn conjugate(self) -> c64 {
@@ -153,3 +165,79 @@ impl std::ops::Div<c64> for c64 {
         )
     }
 }
+
+//#################################################################################################
+//
+//                                  Double precision complex
+//
+//#################################################################################################
+
+// A complex number with two double precision floating point numbers, used for the computations
+// done on the host.
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub(crate) struct c128(pub(crate) f64, pub(crate) f64);
+
+impl c128 {
+    pub(crate) const ZERO: c128 = c128(0.0, 0.0);
+    pub(crate) const ONE: c128 = c128(1.0, 0.0);
+
+    #[inline]
+    pub(crate) fn conjugate(self) -> c128 {
+        c128(self.0, -self.1)
+    }
+
+    #[inline]
+    pub(crate) fn norm_sqr(self) -> f64 {
+        self.0*self.0 + self.1*self.1
+    }
+
+    #[inline]
+    pub(crate) fn norm(self) -> f64 {
+        self.0.hypot(self.1)
+    }
+
+    #[inline]
+    pub(crate) fn scale(self, x: f64) -> c128 {
+        c128(self.0*x, self.1*x)
+    }
+}
+
+impl From<c64> for c128 {
+    fn from(x: c64) -> c128 {
+        c128(x.0 as f64, x.1 as f64)
+    }
+}
+
+impl From<c128> for c64 {
+    fn from(x: c128) -> c64 {
+        c64(x.0 as f32, x.1 as f32)
+    }
+}
+
+impl std::ops::Add<c128> for c128 {
+    type Output = c128;
+
+    fn add(self, rhs: c128) -> c128 {
+        c128(self.0 + rhs.0, self.1 + rhs.1)
+    }
+}
+
+impl std::ops::Sub<c128> for c128 {
+    type Output = c128;
+
+    fn sub(self, rhs: c128) -> c128 {
+        c128(self.0 - rhs.0, self.1 - rhs.1)
+    }
+}
+
+impl std::ops::Mul<c128> for c128 {
+    type Output = c128;
+
+    fn mul(self, rhs: c128) -> c128 {
+        c128(
+            self.0*rhs.0 - self.1*rhs.1,
+            self.0*rhs.1 + self.1*rhs.0,
+        )
+    }
+}
@@ -1,20 +1,63 @@
-use ocl::{Buffer, Kernel, ProQue};
-
 use std::collections::HashMap;
 use std::fmt;
-use std::mem::swap;
+use std::mem::{replace, swap};
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::time::Instant;
 
-use crate::MEASUREMENTS_BLOCK;
+use crate::bitstring::{BitOrder, BitString};
 use crate::complex::c64;
+use crate::density::DensityMatrix;
+use crate::device::{available_devices, select_device, DeviceInfo, DeviceSelector};
+use crate::future::RunFuture;
 use crate::gates::Gate;
+use crate::gpu::GpuState;
 use crate::measure::Measurements;
-use crate::program::{Program, ProgramBuilder};
-use crate::random::MWC64X;
+use crate::mps::MatrixProductState;
+use crate::noise::NoiseModel;
+use crate::prepared::{PreparedState, Readout, Sampler};
+use crate::program::{InitialState, Instruction, Operation, Program, ProgramBuilder};
+use crate::random::{Philox, SeedSource, Stream};
+use crate::readout::ConfusionMatrix;
+use crate::sampling::Sampling;
+use crate::sparse::SparseState;
+use crate::stabilizer::{first_non_clifford, Tableau};
+use crate::state::StateVector;
 
 /// Represents a qbit's address in the quantum computer.
 pub type Address = u8;
 
+//#################################################################################################
+//
+//                                         Backend
+//
+//#################################################################################################
+
+/// The way the state of the computer is represented and simulated.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// The state is a pure vector of `2^n` amplitudes. This is the default.
+    StateVector,
+    /// The state is a density matrix of `2^n × 2^n` entries, allowing the representation
+    /// of mixed states. Gates are applied as `UρU†`.
+    DensityMatrix,
+    /// The state is a stabilizer tableau of `O(n²)` bits, simulated on the host with the
+    /// algorithm of Aaronson and Gottesman. Only Clifford gates (such as H, S, X, Y, Z and their
+    /// controlled Pauli versions, CNOT included) are supported, but on many more qbits.
+    Stabilizer,
+    /// The state is a matrix product state, simulated on the host. The memory needed grows with
+    /// the entanglement of the state rather than with the number of qbits, which suits
+    /// low-entanglement circuits on many qbits. Two-qbit gates between distant qbits are applied
+    /// by swapping their qbits next to each other. The maximal bond dimension and the truncation
+    /// threshold are set with `ComputerBuilder::max_bond_dimension` and
+    /// `ComputerBuilder::truncation_threshold`.
+    MatrixProductState,
+    /// The state is a sparse vector storing only the nonzero amplitudes, simulated on the host.
+    /// It suits circuits that keep few basis states populated, such as reversible arithmetic on
+    /// classical inputs. The number of nonzero amplitudes is bounded by
+    /// `ComputerBuilder::max_support`.
+    Sparse,
+}
+
 //#################################################################################################
 //
 //                                       Computer Builder
@@ -26,10 +69,257 @@ pub struct ComputerBuilder {
     size: Address,
     gates: HashMap<&'static str, Gate>,
     gates_inverses: HashMap<&'static str, Gate>,
+    backend: Backend,
+    noise_model: Option<NoiseModel>,
+    readout_errors: HashMap<Address, ConfusionMatrix>,
+    max_bond_dimension: usize,
+    truncation_threshold: f64,
+    max_support: usize,
+    measurement_block: usize,
+    sampling: Sampling,
+    platform: DeviceSelector,
+    device: DeviceSelector,
+    bit_order: BitOrder,
     built: bool,
 }
 
 impl ComputerBuilder {
+    /// Selects the backend used to represent the state of the computer (default: 
+    /// `Backend::StateVector`).
+    pub fn backend(&mut self, backend: Backend) -> &mut ComputerBuilder {
+        assert!(
+            !self.built,
+            "Computer has already been built, cannot modify it any more",
+        );
+
+        self.backend = backend;
+        self
+    }
+
+    /// Sets the maximal dimension of the bonds of the `Backend::MatrixProductState` backend
+    /// (default: `64`). Larger bonds represent more entangled states, at the cost of memory and time.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if `max_bond_dimension` is 0.
+    pub fn max_bond_dimension(&mut self, max_bond_dimension: usize) -> &mut ComputerBuilder {
+        assert!(
+            !self.built,
+            "Computer has already been built, cannot modify it any more",
+        );
+        assert!(
+            max_bond_dimension != 0,
+            "Maximal bond dimension cannot be 0",
+        );
+
+        self.max_bond_dimension = max_bond_dimension;
+        self
+    }
+
+    /// Sets the truncation threshold of the `Backend::MatrixProductState` backend (default: `1e-12`):
+    /// after each two-qbit gate, the smallest singular values of the split bond are discarded as long
+    /// as the sum of their squares, relatively to the one of all of them, stays below the threshold.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if `truncation_threshold` is not in `[0, 1)`.
+    pub fn truncation_threshold(&mut self, truncation_threshold: f64) -> &mut ComputerBuilder {
+        assert!(
+            !self.built,
+            "Computer has already been built, cannot modify it any more",
+        );
+        assert!(
+            (0.0..1.0).contains(&truncation_threshold),
+            "The truncation threshold {} is not in [0, 1)",
+            truncation_threshold,
+        );
+
+        self.truncation_threshold = truncation_threshold;
+        self
+    }
+
+    /// Sets the maximal number of nonzero amplitudes of the `Backend::Sparse` backend
+    /// (default: `2^20`). Running a program whose state has a larger support will panic.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if `max_support` is 0.
+    pub fn max_support(&mut self, max_support: usize) -> &mut ComputerBuilder {
+        assert!(
+            !self.built,
+            "Computer has already been built, cannot modify it any more",
+        );
+        assert!(
+            max_support != 0,
+            "Maximal support cannot be 0",
+        );
+
+        self.max_support = max_support;
+        self
+    }
+
+    /// Sets the number of samples drawn at once on the device by the `Backend::StateVector` and
+    /// `Backend::DensityMatrix` backends with `Sampling::Tree` (default: `1024`). Larger blocks
+    /// need fewer calls to the device, at the cost of memory and of the samples drawn in excess by
+    /// the last block.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if `measurement_block` is 0.
+    pub fn measurement_block(&mut self, measurement_block: usize) -> &mut ComputerBuilder {
+        assert!(
+            !self.built,
+            "Computer has already been built, cannot modify it any more",
+        );
+        assert!(
+            measurement_block != 0,
+            "Measurement block cannot be 0",
+        );
+
+        self.measurement_block = measurement_block;
+        self
+    }
+
+    /// Selects the way the `Backend::StateVector` and `Backend::DensityMatrix` backends draw
+    /// samples (default: `Sampling::Auto`).
+    pub fn sampling(&mut self, sampling: Sampling) -> &mut ComputerBuilder {
+        assert!(
+            !self.built,
+            "Computer has already been built, cannot modify it any more",
+        );
+
+        self.sampling = sampling;
+        self
+    }
+
+    /// Selects the OpenCL platform of the `Backend::StateVector` and `Backend::DensityMatrix`
+    /// backends, by index, name or kind of device (default: `DeviceSelector::First`, letting OpenCL
+    /// choose if no device is selected either). The available platforms and devices are listed by
+    /// `Computer::available_devices`.
+    /// 
+    /// ```no_run
+    /// use trident::{Computer, DeviceKind};
+    /// 
+    /// let computer = Computer::new(20)
+    ///     .add_default_gates()
+    ///     .platform("intel")
+    ///     .device(DeviceKind::Gpu)
+    ///     .build();
+    /// ```
+    pub fn platform<S>(&mut self, selector: S) -> &mut ComputerBuilder
+    where
+        S: Into<DeviceSelector>,
+    {
+        assert!(
+            !self.built,
+            "Computer has already been built, cannot modify it any more",
+        );
+
+        self.platform = selector.into();
+        self
+    }
+
+    /// Selects the OpenCL device of the `Backend::StateVector` and `Backend::DensityMatrix`
+    /// backends among the ones of the selected platform, or of every platform if none was
+    /// selected, by index, name or kind (default: `DeviceSelector::First`).
+    pub fn device<S>(&mut self, selector: S) -> &mut ComputerBuilder
+    where
+        S: Into<DeviceSelector>,
+    {
+        assert!(
+            !self.built,
+            "Computer has already been built, cannot modify it any more",
+        );
+
+        self.device = selector.into();
+        self
+    }
+
+    /// Sets the order in which the qbits are written in kets and read as integers (default:
+    /// `BitOrder::BigEndian`, qbit #0 being written first). It is used to parse initial states,
+    /// to display measurements, state vectors and density matrices, and to decode registers.
+    /// 
+    /// A state is displayed as it was written:
+    /// 
+    /// ```
+    /// use trident::{Backend, BitOrder, Computer};
+    /// 
+    /// for order in [BitOrder::BigEndian, BitOrder::LittleEndian] {
+    ///     let mut computer = Computer::new(3)
+    ///         .backend(Backend::Sparse)
+    ///         .bit_order(order)
+    ///         .build();
+    /// 
+    ///     let program = computer.new_program("|110>").measure(1);
+    ///     assert_eq!(computer.state_vector(&program).to_string(), "|110>");
+    /// 
+    ///     let results = computer.run(program, None);
+    ///     assert_eq!(results.n_most(1)[0].display(order).to_string(), "110");
+    ///     assert_eq!(results.integer_counts(0..3, None).keys().next(), Some(&0b110));
+    /// }
+    /// ```
+    /// 
+    /// But the qbits it refers to depend on the bit order:
+    /// 
+    /// ```
+    /// use trident::{Backend, BitOrder, Computer};
+    /// 
+    /// let mut computer = Computer::new(3)
+    ///     .backend(Backend::Sparse)
+    ///     .bit_order(BitOrder::LittleEndian)
+    ///     .build();
+    /// 
+    /// let program = computer.new_program("|001>").measure(1);
+    /// let results = computer.run(program, None);
+    /// 
+    /// // Qbit #0 is written last
+    /// assert!(results.n_most(1)[0].get(0));
+    /// assert_eq!(results.n_most(1)[0].display(BitOrder::BigEndian).to_string(), "100");
+    /// ```
+    pub fn bit_order(&mut self, bit_order: BitOrder) -> &mut ComputerBuilder {
+        assert!(
+            !self.built,
+            "Computer has already been built, cannot modify it any more",
+        );
+
+        self.bit_order = bit_order;
+        self
+    }
+
+    /// Attaches a noise model to the computer: it's channels will be applied after each instruction,
+    /// by running Monte-Carlo trajectories on the state vector.
+    pub fn noise_model(&mut self, noise_model: &NoiseModel) -> &mut ComputerBuilder {
+        assert!(
+            !self.built,
+            "Computer has already been built, cannot modify it any more",
+        );
+
+        self.noise_model = Some(noise_model.clone());
+        self
+    }
+
+    /// Sets the readout `confusion` matrix of the qbit #`qbit`, used to randomly flip the measured bits 
+    /// of that qbit when sampling. It overrides the readout error of the noise model, if any.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if `qbit` is out of the register.
+    pub fn readout_error(&mut self, qbit: Address, confusion: ConfusionMatrix) -> &mut ComputerBuilder {
+        assert!(
+            !self.built,
+            "Computer has already been built, cannot modify it any more",
+        );
+        assert!(
+            qbit < self.size,
+            "Qbit's address (#{}) is out of the {}-sized register", 
+            qbit,
+            self.size,
+        );
+
+        self.readout_errors.insert(qbit, confusion);
+        self
+    }
+
     /// Register a new gate for the Computer being build.
     /// 
     /// # Panics
@@ -60,7 +350,7 @@ impl ComputerBuilder {
         let sqrt2inv = 2f32.sqrt().recip();
 
         unsafe {
-            self.add_gate("1", Gate::new_unchecked(1, 0, 1, 0))
+            self.add_gate("1", Gate::new_unchecked(1, 0, 0, 1))
                 .add_gate("H", Gate::new_unchecked(sqrt2inv,sqrt2inv, sqrt2inv, -sqrt2inv))
                 .add_gate("X", Gate::new_unchecked(0, 1, 1, 0))
                 .add_gate("Y", Gate::new_unchecked(0, -c64::I, c64::I, 0))
@@ -72,8 +362,12 @@ impl ComputerBuilder {
     /// 
     /// # Panics
     /// 
-    /// This function will panic if something goes wrong when initializing opencl,
-    /// compiling the shader or allocating memory on the gpu.
+    /// This function will panic if the state of the `Backend::StateVector` backend, or the
+    /// density matrix of the `Backend::DensityMatrix` backend, needs more bits to be addressed than
+    /// the device's address size, before initializing anything. It will panic if something goes
+    /// wrong when initializing opencl, compiling the shader or allocating memory on the gpu, or if
+    /// no OpenCL device matches the selectors. It will also panic if the noise model is used with
+    /// another backend than `Backend::StateVector`, or refers to unknown gates or qbits.
     pub fn build(&mut self) -> Computer {
         assert!(
             !self.built,
@@ -81,21 +375,45 @@ impl ComputerBuilder {
         );
         
         let size = self.size;
-        let dim = 1usize << size;
-
-        let pro_que = ProQue::builder()
-            .src(include_str!("opencl/kernels.cl"))
-            .dims(dim)
-            .build()
-            .expect("Cannot build compute shader");
+        let backend = self.backend;
 
-        let main_buffer = pro_que.create_buffer()
-            .expect("Cannot create main buffer");
+        let buffer_qbits = match backend {
+            Backend::StateVector => Some(size as usize),
+            Backend::DensityMatrix => Some(2 * size as usize),
+            _ => None,
+        };
+        let ptr_size = 8 * std::mem::size_of::<usize>();
+        if let Some(buffer_qbits) = buffer_qbits.filter(|&bits| bits >= ptr_size) {
+            panic!(
+                "Computer's register's size is {}, but the device's address size are only {} bits wide: the {:?} backend needs at least {} bit(s) more",
+                size,
+                ptr_size,
+                backend,
+                buffer_qbits + 1 - ptr_size,
+            );
+        }
 
-        let measurements_buffer = pro_que.buffer_builder()
-            .len(MEASUREMENTS_BLOCK)
-            .build()
-            .expect("Cannot create measurements buffer");
+        if let Some(noise_model) = &self.noise_model {
+            assert!(
+                backend == Backend::StateVector,
+                "Noise models are only supported by the StateVector backend",
+            );
+            for gate_name in noise_model.gate_channels.keys() {
+                assert!(
+                    self.gates.contains_key(gate_name),
+                    "The noise model refers to the gate \"{}\", but no gate is associated to that name",
+                    gate_name,
+                );
+            }
+            for &qbit in noise_model.qbit_channels.keys().chain(noise_model.readout_errors.keys()) {
+                assert!(
+                    qbit < size,
+                    "The noise model refers to qbit #{}, which is out of the {}-sized register",
+                    qbit,
+                    size,
+                );
+            }
+        }
 
         let gates = {
             let mut result = HashMap::with_capacity(0);
@@ -109,59 +427,48 @@ impl ComputerBuilder {
             result
         };
 
-        let apply_gate = pro_que.kernel_builder("apply_gate")
-            .arg(&main_buffer)
-            .arg(0u8)
-            .arg(c64::ZERO)
-            .arg(c64::ZERO)
-            .arg(c64::ZERO)
-            .arg(c64::ZERO)
-            .build()
-            .expect("Cannot build kernel `apply_gate`");
-
-        let apply_controlled_gate = pro_que.kernel_builder("apply_controlled_gate")
-            .arg(&main_buffer)
-            .arg(0u8)
-            .arg(c64::ZERO)
-            .arg(c64::ZERO)
-            .arg(c64::ZERO)
-            .arg(c64::ZERO)
-            .arg(0u8)
-            .build()
-            .expect("Cannot build kernel `apply_controlled_gate`");
-
-        let calculate_probabilities = pro_que.kernel_builder("calculate_probabilities")
-            .arg(&main_buffer)
-            .build()
-            .expect("Cannot build kernel `calculate_probabilities`");
-
-        let reduce_distribution = pro_que.kernel_builder("reduce_distribution")
-            .arg(&main_buffer)
-            .arg(0u8)
-            .global_work_size(dim >> 1)
-            .build()
-            .expect("Cannot build kernel `reduce_distribution`");
-
-        let do_measurements = pro_que.kernel_builder("do_measurements")
-            .arg(&main_buffer)
-            .arg(&measurements_buffer)
-            .arg(size)
-            .arg(0u64)
-            .global_work_size(MEASUREMENTS_BLOCK)
-            .build()
-            .expect("Cannot build kernel `do_measurements`");
+        let readout_errors = {
+            let mut result = HashMap::new();
+            if let Some(noise_model) = &self.noise_model {
+                result.extend(noise_model.readout_errors.iter());
+            }
+            result.extend(self.readout_errors.drain());
+
+            // Sorted so that the random numbers are always drawn in the same order
+            let mut result: Vec<_> = result.into_iter().collect();
+            result.sort_by_key(|&(qbit, _)| qbit);
+            result.into()
+        };
+
+        let engine = match backend {
+            Backend::StateVector | Backend::DensityMatrix => {
+                Engine::Gpu(Box::new(GpuState::new(
+                    size,
+                    backend,
+                    self.noise_model.take(),
+                    self.measurement_block,
+                    self.sampling,
+                    select_device(&self.platform, &self.device),
+                )))
+            },
+            Backend::Stabilizer => Engine::Stabilizer,
+            Backend::MatrixProductState => Engine::MatrixProductState {
+                max_bond_dimension: self.max_bond_dimension,
+                truncation_threshold: self.truncation_threshold,
+            },
+            Backend::Sparse => Engine::Sparse {
+                max_support: self.max_support,
+            },
+        };
             
         Computer {
             size,
             gates,
             gates_inverses,
-            main_buffer,
-            measurements_buffer,
-            apply_gate,
-            apply_controlled_gate,
-            calculate_probabilities,
-            reduce_distribution,
-            do_measurements,
+            backend,
+            engine,
+            readout_errors,
+            bit_order: self.bit_order,
         }
     }
 }
@@ -172,18 +479,38 @@ impl ComputerBuilder {
 //
 //#################################################################################################
 
+// The simulator backing the computer.
+pub(crate) enum Engine {
+    Gpu(Box<GpuState>),
+    Stabilizer,
+    MatrixProductState {
+        max_bond_dimension: usize,
+        truncation_threshold: f64,
+    },
+    Sparse {
+        max_support: usize,
+    },
+    // No simulator: the computer only builds programs, or it's simulator is owned by the worker
+    // thread of an asynchronous run, whose future was leaked if the computer is used
+    Detached,
+}
+
+impl Engine {
+    // Panics because the engine was never given back by an asynchronous run.
+    fn lost() -> ! {
+        panic!("The computer's simulator is still owned by an asynchronous run, whose future was leaked")
+    }
+}
+
 /// Represents a quantum computer, with it's memory and capabilities.
 pub struct Computer {
     pub(crate) size: Address,
     pub(crate) gates: HashMap<&'static str, Gate>,
     pub(crate) gates_inverses: HashMap<&'static str, Gate>,
-    main_buffer: Buffer<c64>,
-    measurements_buffer: Buffer<u64>,
-    apply_gate: Kernel,
-    apply_controlled_gate: Kernel,
-    calculate_probabilities: Kernel,
-    reduce_distribution: Kernel,
-    do_measurements: Kernel,
+    backend: Backend,
+    engine: Engine,
+    readout_errors: Box<[(Address, ConfusionMatrix)]>,
+    pub(crate) bit_order: BitOrder,
 }
 
 impl<'computer> Computer {
@@ -191,186 +518,447 @@ impl<'computer> Computer {
     /// 
     /// # Panics
     /// 
-    /// This function will panic if `size` is 0 or greater than the number of bits of the operating
-    /// system's address size.
+    /// This function will panic if `size` is 0. The size of the computers of the
+    /// `Backend::StateVector` and `Backend::DensityMatrix` backends is also limited by the device's
+    /// address size, which `ComputerBuilder::build` checks first.
     pub fn new(size: Address) -> ComputerBuilder {
         if size == 0 {
             panic!("Computer's register's size is 0, it should be at least 1");
         }
-        let ptr_size = 8 * std::mem::size_of::<usize>();
-        if size as usize > ptr_size {
-            panic!(
-                "Computer's register's size is {}, but the device's address size are only {} bits wide: it needs at least {} bit(s) more",
-                size,
-                ptr_size,
-                size as usize - ptr_size,
-            );
-        }
 
         let gates = HashMap::new();
         let gates_inverses = HashMap::new();
+        let backend = Backend::StateVector;
+        let noise_model = None;
+        let readout_errors = HashMap::new();
+        let max_bond_dimension = 64;
+        let truncation_threshold = 1e-12;
+        let max_support = 1 << 20;
+        let measurement_block = 1024;
+        let sampling = Sampling::Auto;
+        let platform = DeviceSelector::First;
+        let device = DeviceSelector::First;
+        let bit_order = BitOrder::BigEndian;
         let built = false;
 
         ComputerBuilder {
             size,
             gates,
             gates_inverses,
+            backend,
+            noise_model,
+            readout_errors,
+            max_bond_dimension,
+            truncation_threshold,
+            max_support,
+            measurement_block,
+            sampling,
+            platform,
+            device,
+            bit_order,
             built,
         }
     }
 
-    pub fn new_program(&self, initial_state: &str) -> ProgramBuilder {
-        ProgramBuilder::new(self, initial_state)
+    /// Returns every OpenCL device of every platform of the machine, which can be selected with
+    /// `ComputerBuilder::platform` and `ComputerBuilder::device`, or none if OpenCL is unavailable.
+    pub fn available_devices() -> Box<[DeviceInfo]> {
+        available_devices()
+    }
+
+    /// Returns the backend used to represent the state of the computer.
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Returns the order in which the qbits are written in kets and read as integers.
+    pub fn bit_order(&self) -> BitOrder {
+        self.bit_order
+    }
+
+    /// Begins the construction of a new program, starting from the `initial_state`: a ket such as
+    /// `"|010>"`, a sum of kets such as `"0.6|00> + 0.8i|11>"`, a slice of amplitudes or a
+    /// `StateVector`. See `InitialState`.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if the initial state is invalid, is not of the size of the computer,
+    /// or has a null norm.
+    pub fn new_program<'s, S>(&self, initial_state: S) -> ProgramBuilder
+    where
+        S: Into<InitialState<'s>>,
+    {
+        ProgramBuilder::new(self, initial_state.into())
+    }
+
+    /// Returns true if all the gates used by the `program` are Clifford gates, meaning it can be run
+    /// by the `Backend::Stabilizer` backend.
+    pub fn is_clifford(&self, program: &Program) -> bool {
+        first_non_clifford(&self.operations(program)).is_none()
+    }
+
+    /// Runs the gates of the `program` on the computer and returns the resulting density matrix,
+    /// without performing any measurement.
+    /// 
+    /// With the `Backend::StateVector` backend, the density matrix is the one of the final pure
+    /// state `|ψ><ψ|`, built on the host. If the computer has a noise model, it is the average of
+    /// the states of every trajectory, whose channels are drawn with a pseudo-random number
+    /// generator seeded from the `seed` source like `Computer::run`. The seed is ignored otherwise.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if something goes wrong while performing computations, if
+    /// the `program` was not built for a computer of the same size, or if the backend is
+    /// `Backend::Stabilizer`.
+    pub fn density_matrix<S>(&mut self, program: &Program, seed: S) -> DensityMatrix
+    where
+        S: Into<SeedSource>,
+    {
+        let operations = self.operations(program);
+        let seed = seed.into().seed();
+
+        let mut result = match &mut self.engine {
+            Engine::Gpu(gpu) => gpu.density_matrix(&program.initial_state, &operations, seed),
+            Engine::Stabilizer => panic!("The Stabilizer backend cannot compute density matrices"),
+            Engine::MatrixProductState { .. } => panic!("The MatrixProductState backend cannot compute density matrices"),
+            Engine::Sparse { .. } => panic!("The Sparse backend cannot compute density matrices"),
+            Engine::Detached => Engine::lost(),
+        };
+
+        result.bit_order = self.bit_order;
+        result
+    }
+
+    /// Runs the gates of the `program` on the computer and returns the resulting state vector, without
+    /// performing any measurement. It can be used as the initial state of another program.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if something goes wrong while performing computations, if
+    /// the `program` was not built for a computer of the same size, if the backend is not
+    /// `Backend::StateVector` or `Backend::Sparse`, or if the computer has a noise model.
+    pub fn state_vector(&mut self, program: &Program) -> StateVector {
+        let operations = self.operations(program);
+        let size = self.size;
+
+        let amplitudes = match &mut self.engine {
+            Engine::Gpu(gpu) if self.backend == Backend::StateVector => {
+                assert!(
+                    gpu.trajectories().is_none(),
+                    "The state of a computer with a noise model is mixed, it has no state vector",
+                );
+
+                gpu.state_vector(&program.initial_state, &operations)
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, amplitude)| *amplitude != c64::ZERO)
+                    .map(|(state, amplitude)| (BitString::from_u64(state as u64, size as usize), amplitude))
+                    .collect()
+            },
+            Engine::Sparse { max_support } => {
+                let mut sparse = SparseState::new(size, &program.initial_state, *max_support);
+                sparse.apply(&operations);
+                sparse.amplitudes()
+                    .map(|(state, amplitude)| (state.clone(), amplitude.into()))
+                    .collect()
+            },
+            Engine::Detached => Engine::lost(),
+            _ => panic!("The {:?} backend cannot compute state vectors", self.backend),
+        };
+
+        StateVector::new(size, amplitudes, true, self.bit_order)
+    }
+
+    /// Runs the gates of the `program` on the computer and returns the resulting matrix product state,
+    /// without performing any measurement. It gives access to amplitudes, bond dimensions and to the
+    /// truncation error.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if the `program` was not built for a computer of the same size, or if
+    /// the backend is not `Backend::MatrixProductState`.
+    pub fn matrix_product_state(&self, program: &Program) -> MatrixProductState {
+        let operations = self.operations(program);
+
+        match self.engine {
+            Engine::MatrixProductState { max_bond_dimension, truncation_threshold } => {
+                let mut mps = MatrixProductState::new(
+                    self.size, 
+                    &program.initial_state, 
+                    max_bond_dimension, 
+                    truncation_threshold,
+                );
+                mps.apply(&operations);
+                mps
+            },
+            Engine::Detached => Engine::lost(),
+            _ => panic!("The {:?} backend cannot compute matrix product states", self.backend),
+        }
+    }
+
+    // Resolves the gates of the instructions of the `program`.
+    fn operations(&self, program: &Program) -> Box<[Operation]> {
+        assert!(
+            program.size == self.size,
+            "The program was built for a computer of size {}, but this one is of size {}",
+            program.size,
+            self.size,
+        );
+
+        program.instructions.iter()
+            .map(|instruction| Operation {
+                gate_name: instruction.gate_name,
+                gate: self.gate(instruction),
+                target: instruction.target,
+                control: instruction.control,
+            })
+            .collect()
+    }
+
+    // Returns the gate corresponding to the instruction.
+    #[inline]
+    fn gate(&self, instruction: &Instruction) -> Gate {
+        if instruction.reverse {
+            self.gates_inverses[instruction.gate_name]
+        } else {
+            self.gates[instruction.gate_name]
+        }
+    }
+
+    // Resolves the gates of the instructions of the `program`, followed by the rotations of the
+    // measured qbits into their measurement basis.
+    fn measured_operations(&self, program: &Program) -> Vec<Operation> {
+        let mut operations = self.operations(program).into_vec();
+        for (&qbit, pauli) in program.measured.iter().zip(program.bases.iter()) {
+            for (gate_name, gate) in pauli.basis_change() {
+                operations.push(Operation {
+                    gate_name,
+                    gate,
+                    target: qbit,
+                    control: None,
+                });
+            }
+        }
+        operations
+    }
+
+    // Returns the readout errors of the measured `qbits`, at their position in the measured states.
+    fn measured_readout_errors(&self, qbits: &[Address]) -> Box<[(Address, ConfusionMatrix)]> {
+        self.readout_errors.iter()
+            .filter_map(|&(qbit, confusion)| {
+                qbits.iter()
+                    .position(|&measured| measured == qbit)
+                    .map(|position| (position as Address, confusion))
+            })
+            .collect()
+    }
+
+    /// Runs the gates of the `program` on the computer, and returns the resulting state ready to be
+    /// sampled many times with `PreparedState::sample`, or to be queried for the exact probability of
+    /// the measured states with `PreparedState::probability`, without running the gates again. The
+    /// number of samples of the `program` is ignored, but not it's measured qbits, bases, registers
+    /// and kept shots.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if something goes wrong while performing computations, if the
+    /// program uses gates the backend does not support, or if the computer has a noise model
+    /// simulated with trajectories, every trajectory being a different state.
+    pub fn prepare(&mut self, program: &Program) -> PreparedState<'_> {
+        let start = Instant::now();
+
+        let operations = self.measured_operations(program);
+        let readout_errors = self.measured_readout_errors(&program.measured);
+        let bit_order = self.bit_order;
+        let size = self.size;
+
+        let sampler = match &mut self.engine {
+            Engine::Gpu(gpu) => {
+                assert!(
+                    gpu.trajectories().is_none(),
+                    "The state of a computer with a noise model is different for every trajectory, it cannot be prepared",
+                );
+
+                gpu.prepare_state(&program.initial_state, &operations, None);
+                let distribution = gpu.prepare_distribution(&program.measured);
+                Sampler::Gpu(gpu, distribution)
+            },
+            Engine::Stabilizer => {
+                let initial_state = program.initial_state.basis_state()
+                    .unwrap_or_else(|| panic!("The Stabilizer backend only supports computational basis states as initial states"))
+                    .0;
+
+                let mut tableau = Tableau::new(size, initial_state);
+                tableau.apply(&operations);
+                Sampler::Stabilizer(tableau.measure_all())
+            },
+            Engine::MatrixProductState { max_bond_dimension, truncation_threshold } => {
+                let mut mps = MatrixProductState::new(
+                    size, 
+                    &program.initial_state, 
+                    *max_bond_dimension, 
+                    *truncation_threshold,
+                );
+                mps.apply(&operations);
+                Sampler::MatrixProductState(mps)
+            },
+            Engine::Sparse { max_support } => {
+                let mut sparse = SparseState::new(size, &program.initial_state, *max_support);
+                sparse.apply(&operations);
+                Sampler::Sparse(sparse)
+            },
+            Engine::Detached => Engine::lost(),
+        };
+
+        PreparedState::new(
+            Instant::now().duration_since(start),
+            program,
+            readout_errors,
+            bit_order,
+            sampler,
+        )
     }
 
-    /// Runs the `program` on the computer. Uses, if provided, `seed` as the seed of the
-    /// pseudo-random number generator to allow recreation of results. If `seed` is `None`, the system's
-    /// time will be used as a seed.
+    /// Runs the `program` on the computer. The pseudo-random number generator is seeded from the
+    /// `seed` source: a seed to recreate the results of a previous run, `None` for a fresh seed
+    /// drawn from the operating system's entropy, or with the `rand_core` feature a `&mut RngCore`
+    /// to draw the seed from. The seed used is recorded in the measurements.
     /// 
     /// Returns a Measurements struct, containing all needed information and results about the computation.
     /// 
     /// # Panics
     /// 
     /// This function will panic if something goes wrong while performing computations, such as the
-    /// buffer being unwritable/unreadable or the kernels crashing somehow.
+    /// buffer being unwritable/unreadable or the kernels crashing somehow, or if the program uses
+    /// gates the backend does not support.
     pub fn run<S>(&mut self, program: Program, seed: S) -> Measurements
     where
-        S: Into<Option<u64>>,
+        S: Into<SeedSource>,
     {
         let start = Instant::now();
+        let seed = seed.into().seed();
 
-        // Initialization of amplitudes buffer
-        unsafe {
-            *self.main_buffer.map()
-                .write_invalidate()
-                .enq()
-                .expect("Cannot write to the main buffer")
-                .get_mut(program.initial_state)
-                .expect("Cannot access element in the main buffer")
-            = c64::ONE;
-        }
+        let trajectories = match &self.engine {
+            Engine::Gpu(gpu) => gpu.trajectories(),
+            Engine::Detached => Engine::lost(),
+            _ => None,
+        };
+        let trajectories = match trajectories {
+            Some(trajectories) => std::cmp::min(trajectories, program.samples),
+            None => return self.prepare(&program).measure(program.samples, seed, start),
+        };
 
-        // Apply gates
-        for instruction in program.instructions.iter() {
-            let target = instruction.target;
-
-            let gate = if instruction.reverse {
-                self.gates[instruction.gate_name]
-            } else {
-                self.gates_inverses[instruction.gate_name]
-            };
-
-            let kernel = if let Some(control) = instruction.control {
-                let kernel = &self.apply_controlled_gate;
-                kernel.set_arg(6, control).unwrap();
-                kernel
-            } else {
-                &self.apply_gate
-            };
-
-            kernel.set_arg(1, target).unwrap();
-
-            kernel.set_arg(2, gate.u00).unwrap();
-            kernel.set_arg(3, gate.u01).unwrap();
-            kernel.set_arg(4, gate.u10).unwrap();
-            kernel.set_arg(5, gate.u11).unwrap();
-
-            unsafe { 
-                kernel.enq()
-                    .expect("Cannot call kernel `apply_gate` or `apply_gate_controlled`");
-            }
-        }
+        // With a noise model every trajectory is a different state, the samples are evenly split
+        // between them
+        let operations = self.measured_operations(&program);
+        let readout_errors = self.measured_readout_errors(&program.measured);
 
-        // Calculate the probabilities vector
-        unsafe { 
-            self.calculate_probabilities.enq()
-                .expect("Cannot call kernel `calculate_probabilities`");
-        }
+        let prng = Philox::new(seed);
 
-        // Reduce probabilities
-        {
-            let mut worksize: usize = 1 << (self.size - 1);
+        let mut results = HashMap::with_capacity(program.samples);
+        let mut readout = Readout::new(&readout_errors, &prng, program.kept_shots, program.measured.len(), program.samples);
 
-            for pass in 1..self.size {
-                self.reduce_distribution.set_default_global_work_size(worksize.into());
-                self.reduce_distribution.set_arg(1, pass).unwrap();
+        if let Engine::Gpu(gpu) = &mut self.engine {
+            let mut first = 0;
 
-                unsafe {
-                    self.reduce_distribution.enq()
-                        .expect("Cannot call kernel `reduce_distribution`");
-                }
+            for i in 0..trajectories {
+                let samples = program.samples / trajectories + (i < program.samples % trajectories) as usize;
 
-                worksize >>= 1;
+                let mut noise = prng.substream(Stream::Noise, i as u64);
+                gpu.prepare_state(&program.initial_state, &operations, Some(&mut noise));
+                gpu.sample(samples, first, &program.measured, &prng, &mut results, |state| readout.apply(state));
+
+                first += samples as u64;
             }
         }
 
-        {
-            let mut prng = MWC64X::new(seed.into());
-            // Skips the first few numbers as they tend to be of poorer quality
-            prng.skip(1000);
-
-            let mut buffer = vec![0; MEASUREMENTS_BLOCK];
-            let mut results = HashMap::with_capacity(program.samples);
-            let mut remaining = program.samples;
-
-            while remaining != 0 {
-                let measures = std::cmp::min(remaining, MEASUREMENTS_BLOCK);
-                remaining -= measures;
-
-                prng.skip(MEASUREMENTS_BLOCK as u64);
-                self.do_measurements.set_arg(3, prng.state()).unwrap();
-
-                unsafe {
-                    self.do_measurements.enq()
-                        .expect("Cannot call kernel `do_measurements`");
-                }
-
-                self.measurements_buffer.read(&mut buffer)
-                    .enq()
-                    .expect("Cannot read from buffer `measurements`");
-
-                for state in buffer.iter().take(measures) {
-                    if let Some(freq) = results.get_mut(state) {
-                        *freq += 1;
-                    } else {
-                        results.insert(*state, 1);
-                    }
-                }
-            }
-        
-            Measurements::new(
-                Instant::now().duration_since(start),
-                self.size,
-                program.samples,
-                results,
-            )
+        let shots = readout.into_shots();
+
+        Measurements::new(
+            Instant::now().duration_since(start),
+            program.measured,
+            program.bases,
+            program.samples,
+            results,
+            program.registers,
+            self.bit_order,
+            shots,
+            seed,
+        )
+    }
+
+    /// Runs the `program` like `Computer::run`, but on a worker thread, and returns the future of the
+    /// measurements. The calling thread is not blocked by the computations and the reads from the
+    /// device, it can for instance process the results of a previous run in the meantime. The seed is
+    /// drawn from the `seed` source before returning.
+    ///
+    /// The computer is borrowed until the future completes or is dropped, dropping it waiting for
+    /// the end of the run.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the worker thread cannot be spawned. Awaiting the future will panic
+    /// in the same cases as `Computer::run`.
+    pub fn run_async<S>(&mut self, program: Program, seed: S) -> RunFuture<'_>
+    where
+        S: Into<SeedSource>,
+    {
+        let seed = seed.into().seed();
+
+        // The worker runs the program with the engine of the computer, and gives it back
+        let mut worker = self.detached();
+        worker.engine = replace(&mut self.engine, Engine::Detached);
+
+        RunFuture::new(self, move || {
+            let measurements = catch_unwind(AssertUnwindSafe(|| worker.run(program, seed)));
+            (worker.engine, measurements)
+        })
+    }
+
+    // Returns a copy of the computer without it's simulator, which can only build programs.
+    pub(crate) fn detached(&self) -> Computer {
+        Computer {
+            size: self.size,
+            gates: self.gates.clone(),
+            gates_inverses: self.gates_inverses.clone(),
+            backend: self.backend,
+            engine: Engine::Detached,
+            readout_errors: self.readout_errors.clone(),
+            bit_order: self.bit_order,
         }
     }
-}
 
-/*
-/// Represents a quantum computer, with it's memory and capabilities.
-pub struct Computer {
-    pub(crate) size: Address,
-    pub(crate) gates: HashMap<&'static str, Gate>,
-    pub(crate) gates_inverses: HashMap<&'static str, Gate>,
-    main_buffer: Buffer<c64>,
-    measurements_buffer: Buffer<u64>,
-    apply_gate: Kernel,
-    apply_controlled_gate: Kernel,
-    calculate_probabilities: Kernel,
-    reduce_distribution: Kernel,
-    do_measurements: Kernel,
+    // Gives back the engine owned by an asynchronous run.
+    pub(crate) fn restore_engine(&mut self, engine: Engine) {
+        self.engine = engine;
+    }
 }
-*/
 
 impl fmt::Display for Computer {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let memory = match &self.engine {
+            Engine::Gpu(gpu) => gpu.memory_usage(),
+            Engine::Stabilizer => Tableau::new(self.size, &BitString::zeros(self.size as usize)).memory_usage(),
+            Engine::MatrixProductState { max_bond_dimension, .. } => {
+                MatrixProductState::memory_usage(self.size, *max_bond_dimension)
+            },
+            Engine::Sparse { max_support } => SparseState::memory_usage(*max_support),
+            Engine::Detached => 0,
+        };
+
+        let device = match &self.engine {
+            Engine::Gpu(gpu) => format!("\n  [Device: {}],", gpu.device()),
+            _ => String::new(),
+        };
+
         write!(f, 
-            "[\n  [Computer of size {}],\n  [Memory usage: {} bytes],\n  [Available gates: {:?}]\n]",
+            "[\n  [Computer of size {}],\n  [Backend: {:?}],{}\n  [Bit order: {:?}],\n  [Memory usage: {} bytes],\n  [Available gates: {:?}]\n]",
             self.size,
-            ((1usize << self.size) + MEASUREMENTS_BLOCK) * 8,
+            self.backend,
+            device,
+            self.bit_order,
+            memory,
             self.gates.keys().map(|s| *s).collect::<Box<[&'static str]>>(),
         )
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,293 @@
+use std::fmt;
+
+use crate::bitstring::{BitOrder, BitString};
+use crate::complex::c64;
+use crate::computer::Address;
+use crate::linalg::hermitian_eigenvalues;
+
+//#################################################################################################
+//
+//                                     Helper functions
+//
+//#################################################################################################
+
+// Spreads the bits of `value` over the positions given by `qbits`: the bit #i of `value` becomes
+// the bit #qbits[i] of the result.
+#[inline]
+fn deposit(value: usize, qbits: &[Address]) -> usize {
+    qbits.iter()
+        .enumerate()
+        .filter(|(i, _)| value & (1 << i) != 0)
+        .fold(0, |acc, (_, &qbit)| acc | 1 << qbit)
+}
+
+//#################################################################################################
+//
+//                                      Density Matrix
+//
+//#################################################################################################
+
+/// Represents the density matrix `ρ` of a (possibly mixed) state of `size` qbits.
+pub struct DensityMatrix {
+    size: Address,
+    // Column major: ρ[r][c] is stored at index r + c*2^size
+    data: Box<[c64]>,
+    // The order in which the basis states are displayed, set by the computer
+    pub(crate) bit_order: BitOrder,
+}
+
+impl DensityMatrix {
+    pub(crate) fn new(size: Address, data: Box<[c64]>) -> DensityMatrix {
+        DensityMatrix {
+            size,
+            data,
+            bit_order: BitOrder::BigEndian,
+        }
+    }
+
+    // Builds the density matrix of the uniform mixture of the given pure states.
+    pub(crate) fn from_state_vectors(size: Address, states: &[Vec<c64>]) -> DensityMatrix {
+        let dim = 1usize << size;
+        let weight = c64::from(states.len()).recip();
+
+        let mut data = vec![c64::ZERO; dim * dim];
+        for amplitudes in states {
+            for (col, a) in amplitudes.iter().enumerate() {
+                for (row, b) in amplitudes.iter().enumerate() {
+                    data[row + col * dim] = data[row + col * dim] + *b * a.conjugate() * weight;
+                }
+            }
+        }
+
+        DensityMatrix::new(size, data.into())
+    }
+
+    /// Returns the number of qbits the density matrix describes.
+    pub fn size(&self) -> Address {
+        self.size
+    }
+
+    /// Returns the dimension of the matrix, that is `2^size`.
+    pub fn dim(&self) -> usize {
+        1 << self.size
+    }
+
+    /// Returns the coefficient `ρ[row][col]`, that is `<row|ρ|col>`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `row` or `col` is greater or equal to the matrix's dimension.
+    pub fn get(&self, row: usize, col: usize) -> c64 {
+        let dim = self.dim();
+        assert!(
+            row < dim && col < dim,
+            "Index ({}, {}) is out of the {}x{} density matrix",
+            row,
+            col,
+            dim,
+            dim,
+        );
+
+        self.data[row + col * dim]
+    }
+
+    /// Returns the trace of the matrix, which should be close to `1`.
+    pub fn trace(&self) -> f64 {
+        (0..self.dim())
+            .map(|i| self.get(i, i).re() as f64)
+            .sum()
+    }
+
+    /// Returns the purity `Tr(ρ²)` of the state, which is `1` for pure states and `1/2^size` for the
+    /// maximally mixed state.
+    pub fn purity(&self) -> f64 {
+        // ρ being hermitian, Tr(ρ²) is the sum of the squared norms of it's coefficients
+        self.data.iter()
+            .map(|x| x.norm_sqr() as f64)
+            .sum()
+    }
+
+    /// Returns the eigenvalues of `ρ`, sorted in decreasing order.
+    pub fn eigenvalues(&self) -> Box<[f64]> {
+        hermitian_eigenvalues(self.dim(), &self.data)
+    }
+
+    /// Returns the von Neumann entropy `S(ρ) = -Tr(ρ log2 ρ)` of the state, in bits.
+    pub fn von_neumann_entropy(&self) -> f64 {
+        self.eigenvalues()
+            .iter()
+            .filter(|&&lambda| lambda > 1e-12)
+            .map(|&lambda| -lambda * lambda.log2())
+            .sum()
+    }
+
+    /// Traces out the qbits in `traced` and returns the reduced density matrix of the remaining
+    /// qbits. The remaining qbits keep their relative order: the lowest of them becomes qbit #0 of
+    /// the reduced matrix, and so on.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if one of the addresses is out of the register, is given twice, or if
+    /// all the qbits are traced out.
+    pub fn partial_trace(&self, traced: &[Address]) -> DensityMatrix {
+        let mut is_traced = vec![false; self.size as usize];
+        for &qbit in traced {
+            assert!(
+                qbit < self.size,
+                "Traced qbit's address (#{}) is out of the {}-sized register",
+                qbit,
+                self.size,
+            );
+            assert!(
+                !is_traced[qbit as usize],
+                "Qbit #{} is traced out twice",
+                qbit,
+            );
+            is_traced[qbit as usize] = true;
+        }
+
+        let kept: Box<[Address]> = (0..self.size)
+            .filter(|&qbit| !is_traced[qbit as usize])
+            .collect();
+
+        assert!(
+            !kept.is_empty(),
+            "Cannot trace out every qbit of the density matrix",
+        );
+
+        let size = kept.len() as Address;
+        let dim = 1usize << size;
+        let traced_dim = 1usize << traced.len();
+
+        let mut data = vec![c64::ZERO; dim * dim];
+        for col in 0..dim {
+            let col_bits = deposit(col, &kept);
+            for row in 0..dim {
+                let row_bits = deposit(row, &kept);
+                data[row + col * dim] = (0..traced_dim)
+                    .map(|t| deposit(t, traced))
+                    .fold(c64::ZERO, |acc, t| acc + self.get(row_bits | t, col_bits | t));
+            }
+        }
+
+        let mut result = DensityMatrix::new(size, data.into());
+        result.bit_order = self.bit_order;
+        result
+    }
+}
+
+impl fmt::Display for DensityMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+            "[\n  [Density matrix of {} qbits],\n  [Trace: {:.5}],\n  [Purity: {:.5}],\n  [Diagonal:\n",
+            self.size,
+            self.trace(),
+            self.purity(),
+        ).unwrap();
+
+        let dim = self.dim();
+
+        for i in 0..dim {
+            writeln!(f,
+                "    |{}> ~> {:?}{}",
+                BitString::from_u64(i as u64, self.size as usize).display(self.bit_order),
+                self.get(i, i),
+                if i+1 == dim {""} else {","},
+            ).unwrap();
+        }
+
+        write!(f, "  ]\n]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-6, "{} is not close to {}", actual, expected);
+    }
+
+    // The density matrix of the pure state of `size` qbits of amplitudes `amplitudes`.
+    fn pure(size: Address, amplitudes: &[f32]) -> DensityMatrix {
+        let amplitudes: Vec<c64> = amplitudes.iter().map(|&a| c64::new(a, 0.0)).collect();
+        DensityMatrix::from_state_vectors(size, &[amplitudes])
+    }
+
+    #[test]
+    fn bell_pair() {
+        let sqrt2inv = 2f32.sqrt().recip();
+        let bell = pure(2, &[sqrt2inv, 0.0, 0.0, sqrt2inv]);
+
+        assert_close(bell.trace(), 1.0);
+        assert_close(bell.purity(), 1.0);
+        assert_close(bell.von_neumann_entropy(), 0.0);
+
+        // Each qbit alone is maximally mixed
+        for &traced in [0, 1].iter() {
+            let reduced = bell.partial_trace(&[traced]);
+            assert_eq!(reduced.size(), 1);
+            assert_close(reduced.get(0, 0).re() as f64, 0.5);
+            assert_close(reduced.get(1, 1).re() as f64, 0.5);
+            assert_close(reduced.get(0, 1).norm() as f64, 0.0);
+            assert_close(reduced.purity(), 0.5);
+            assert_close(reduced.von_neumann_entropy(), 1.0);
+        }
+    }
+
+    #[test]
+    fn product_state() {
+        // |0>|+>, qbit #0 being |+>
+        let sqrt2inv = 2f32.sqrt().recip();
+        let product = pure(2, &[sqrt2inv, sqrt2inv, 0.0, 0.0]);
+
+        assert_close(product.purity(), 1.0);
+        assert_close(product.von_neumann_entropy(), 0.0);
+
+        // Tracing out qbit #1 leaves |+><+| as the qbit #0
+        let plus = product.partial_trace(&[1]);
+        for (row, col) in [(0, 0), (0, 1), (1, 0), (1, 1)].iter() {
+            assert_close(plus.get(*row, *col).re() as f64, 0.5);
+        }
+        assert_close(plus.purity(), 1.0);
+        assert_close(plus.von_neumann_entropy(), 0.0);
+
+        // Tracing out qbit #0 leaves |0><0| as the qbit #0
+        let zero = product.partial_trace(&[0]);
+        assert_close(zero.get(0, 0).re() as f64, 1.0);
+        assert_close(zero.get(1, 1).re() as f64, 0.0);
+        assert_close(zero.purity(), 1.0);
+        assert_close(zero.von_neumann_entropy(), 0.0);
+    }
+
+    #[test]
+    fn mixture() {
+        // The uniform mixture of |0> and |1> is maximally mixed
+        let states = [vec![c64::ONE, c64::ZERO], vec![c64::ZERO, c64::ONE]];
+        let mixed = DensityMatrix::from_state_vectors(1, &states);
+
+        assert_close(mixed.trace(), 1.0);
+        assert_close(mixed.get(0, 0).re() as f64, 0.5);
+        assert_close(mixed.get(0, 1).norm() as f64, 0.0);
+        assert_close(mixed.purity(), 0.5);
+        assert_close(mixed.von_neumann_entropy(), 1.0);
+    }
+
+    #[test]
+    fn kept_qbits_order() {
+        // |1>|0>|1>: tracing out the middle qbit leaves |1>|1>
+        let mut amplitudes = [0.0; 8];
+        amplitudes[0b101] = 1.0;
+        let reduced = pure(3, &amplitudes).partial_trace(&[1]);
+
+        assert_eq!(reduced.size(), 2);
+        assert_close(reduced.get(0b11, 0b11).re() as f64, 1.0);
+        assert_close(reduced.trace(), 1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot trace out every qbit")]
+    fn trace_out_everything() {
+        pure(1, &[1.0, 0.0]).partial_trace(&[0]);
+    }
+}
@@ -0,0 +1,261 @@
+use std::fmt;
+
+use ocl::enums::{DeviceInfo as OclDeviceInfo, DeviceInfoResult};
+use ocl::flags::DeviceType;
+use ocl::{Device, Platform};
+
+//#################################################################################################
+//
+//                                         Selectors
+//
+//#################################################################################################
+
+/// The type of an OpenCL device.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DeviceKind {
+    Cpu,
+    Gpu,
+    Accelerator,
+    /// Any other type, such as custom devices.
+    Other,
+}
+
+impl DeviceKind {
+    // Returns the kind of the OpenCL device type.
+    fn of(device_type: DeviceType) -> DeviceKind {
+        if device_type.contains(DeviceType::GPU) {
+            DeviceKind::Gpu
+        } else if device_type.contains(DeviceType::CPU) {
+            DeviceKind::Cpu
+        } else if device_type.contains(DeviceType::ACCELERATOR) {
+            DeviceKind::Accelerator
+        } else {
+            DeviceKind::Other
+        }
+    }
+}
+
+/// Selects the OpenCL platform or device of a computer, with `ComputerBuilder::platform` and
+/// `ComputerBuilder::device`. Indices, names and kinds convert into selectors.
+///
+/// ```
+/// use trident::{DeviceKind, DeviceSelector};
+///
+/// assert_eq!(DeviceSelector::from(1), DeviceSelector::Index(1));
+/// assert_eq!(DeviceSelector::from("nvidia"), DeviceSelector::Name("nvidia".to_string()));
+/// assert_eq!(DeviceSelector::from(DeviceKind::Cpu), DeviceSelector::Kind(DeviceKind::Cpu));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum DeviceSelector {
+    /// The first one, which is the default.
+    #[default]
+    First,
+    /// The #i one, in the order of `Computer::available_devices`: among the platforms, or among the
+    /// devices of the selected platform, or of every platform if none is selected.
+    Index(usize),
+    /// The first one whose name contains the string, ignoring case.
+    Name(String),
+    /// The first device of the kind, or the first platform with a device of the kind, whose first
+    /// device of the kind is then selected by default.
+    Kind(DeviceKind),
+}
+
+impl From<usize> for DeviceSelector {
+    fn from(index: usize) -> DeviceSelector {
+        DeviceSelector::Index(index)
+    }
+}
+
+impl From<&str> for DeviceSelector {
+    fn from(name: &str) -> DeviceSelector {
+        DeviceSelector::Name(name.to_string())
+    }
+}
+
+impl From<String> for DeviceSelector {
+    fn from(name: String) -> DeviceSelector {
+        DeviceSelector::Name(name)
+    }
+}
+
+impl From<DeviceKind> for DeviceSelector {
+    fn from(kind: DeviceKind) -> DeviceSelector {
+        DeviceSelector::Kind(kind)
+    }
+}
+
+impl DeviceSelector {
+    // Returns true if the #index of the candidates, of the `name` and with devices of the `kinds`,
+    // is selected.
+    fn matches(&self, index: usize, name: &str, kinds: &[DeviceKind]) -> bool {
+        match self {
+            DeviceSelector::First => true,
+            DeviceSelector::Index(i) => *i == index,
+            DeviceSelector::Name(pattern) => name.to_lowercase().contains(&pattern.to_lowercase()),
+            DeviceSelector::Kind(kind) => kinds.contains(kind),
+        }
+    }
+}
+
+//#################################################################################################
+//
+//                                       Available devices
+//
+//#################################################################################################
+
+/// The description of an OpenCL device, listed by `Computer::available_devices`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// The index of the platform of the device.
+    pub platform_index: usize,
+    /// The name of the platform of the device.
+    pub platform: String,
+    /// The index of the device among the devices of it's platform.
+    pub index: usize,
+    /// The name of the device.
+    pub name: String,
+    /// The vendor of the device.
+    pub vendor: String,
+    /// The type of the device.
+    pub kind: DeviceKind,
+    /// The size of the global memory of the device, in bytes.
+    pub global_memory: u64,
+    /// The size of the largest buffer the device can allocate, in bytes.
+    pub max_allocation: u64,
+    /// The number of parallel compute units of the device.
+    pub compute_units: u32,
+    /// Whether the device supports double precision floats.
+    pub fp64: bool,
+}
+
+impl DeviceInfo {
+    // Describes the #index `device` of the #platform_index `platform`.
+    fn new(platform_index: usize, platform: &Platform, index: usize, device: &Device) -> DeviceInfo {
+        let info = |kind| device.info(kind).ok();
+
+        let kind = match info(OclDeviceInfo::Type) {
+            Some(DeviceInfoResult::Type(device_type)) => DeviceKind::of(device_type),
+            _ => DeviceKind::Other,
+        };
+        let global_memory = match info(OclDeviceInfo::GlobalMemSize) {
+            Some(DeviceInfoResult::GlobalMemSize(size)) => size,
+            _ => 0,
+        };
+        let max_allocation = match info(OclDeviceInfo::MaxMemAllocSize) {
+            Some(DeviceInfoResult::MaxMemAllocSize(size)) => size,
+            _ => 0,
+        };
+        let compute_units = match info(OclDeviceInfo::MaxComputeUnits) {
+            Some(DeviceInfoResult::MaxComputeUnits(units)) => units,
+            _ => 0,
+        };
+        let fp64 = match info(OclDeviceInfo::Extensions) {
+            Some(DeviceInfoResult::Extensions(extensions)) => extensions.split_whitespace().any(|e| e == "cl_khr_fp64"),
+            _ => false,
+        };
+
+        DeviceInfo {
+            platform_index,
+            platform: platform.name().unwrap_or_default(),
+            index,
+            name: device.name().unwrap_or_default(),
+            vendor: device.vendor().unwrap_or_default(),
+            kind,
+            global_memory,
+            max_allocation,
+            compute_units,
+            fp64,
+        }
+    }
+}
+
+impl fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+            "[{}.{}] {} ({:?}, {}) on {}: {} MiB, {} compute units, fp64 {}",
+            self.platform_index,
+            self.index,
+            self.name,
+            self.kind,
+            self.vendor,
+            self.platform,
+            self.global_memory >> 20,
+            self.compute_units,
+            if self.fp64 {"supported"} else {"unsupported"},
+        )
+    }
+}
+
+// Returns the OpenCL platforms with their devices, none if OpenCL is unavailable.
+fn platforms() -> Vec<(Platform, Vec<Device>)> {
+    let platforms = ocl::core::get_platform_ids().unwrap_or_default();
+
+    Platform::list_from_core(platforms)
+        .into_iter()
+        .map(|platform| {
+            let devices = Device::list_all(platform).unwrap_or_default();
+            (platform, devices)
+        })
+        .collect()
+}
+
+// Returns every OpenCL device of every platform.
+pub(crate) fn available_devices() -> Box<[DeviceInfo]> {
+    platforms().iter()
+        .enumerate()
+        .flat_map(|(platform_index, (platform, devices))| {
+            devices.iter()
+                .enumerate()
+                .map(move |(index, device)| DeviceInfo::new(platform_index, platform, index, device))
+        })
+        .collect()
+}
+
+// Returns the platform and device selected by the selectors, or `None` to let OpenCL choose if none
+// was given.
+//
+// Panics if no device is selected.
+pub(crate) fn select_device(platform: &DeviceSelector, device: &DeviceSelector) -> Option<(Platform, Device)> {
+    if *platform == DeviceSelector::First && *device == DeviceSelector::First {
+        return None;
+    }
+
+    // A platform selected by kind implies the kind of the device
+    let device = match (platform, device) {
+        (DeviceSelector::Kind(kind), DeviceSelector::First) => DeviceSelector::Kind(*kind),
+        _ => device.clone(),
+    };
+
+    let kind = |device: &Device| match device.info(OclDeviceInfo::Type) {
+        Ok(DeviceInfoResult::Type(device_type)) => DeviceKind::of(device_type),
+        _ => DeviceKind::Other,
+    };
+
+    // The devices of the selected platforms, numbered among all of them
+    let candidates: Vec<(Platform, Device)> = platforms().into_iter()
+        .enumerate()
+        .filter(|(index, (candidate, devices))| {
+            let kinds: Vec<DeviceKind> = devices.iter().map(kind).collect();
+            platform.matches(*index, &candidate.name().unwrap_or_default(), &kinds)
+        })
+        .take(match platform {
+            DeviceSelector::First => usize::MAX,
+            _ => 1,
+        })
+        .flat_map(|(_, (candidate, devices))| devices.into_iter().map(move |device| (candidate, device)))
+        .collect();
+
+    let (_, selected) = candidates.into_iter()
+        .enumerate()
+        .find(|(index, (_, candidate))| {
+            device.matches(*index, &candidate.name().unwrap_or_default(), &[kind(candidate)])
+        })
+        .unwrap_or_else(|| panic!(
+            "No OpenCL device matches the device selector {:?} on the platform selected by {:?}, the available devices are {:?}",
+            device,
+            platform,
+            available_devices().iter().map(DeviceInfo::to_string).collect::<Vec<_>>(),
+        ));
+
+    Some(selected)
+}
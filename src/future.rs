@@ -0,0 +1,128 @@
+use std::future::Future;
+use std::panic::resume_unwind;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, JoinHandle};
+
+use crate::computer::{Computer, Engine};
+use crate::measure::Measurements;
+
+//#################################################################################################
+//
+//                                        Run future
+//
+//#################################################################################################
+
+// The outcome of a run on the worker thread: the engine is given back even if the run panicked.
+pub(crate) type Outcome = (Engine, thread::Result<Measurements>);
+
+// The state shared with the worker thread.
+struct Shared {
+    outcome: Option<Outcome>,
+    waker: Option<Waker>,
+}
+
+/// The future of the measurements of a program run by `Computer::run_async`. The program is run on a
+/// worker thread, which owns the simulator of the computer until the run is over: the computer is
+/// borrowed as long as the future is alive. Dropping the future before it completes waits for the
+/// end of the run.
+///
+/// If the run panics, the panic is resumed when the future is polled.
+pub struct RunFuture<'a> {
+    computer: &'a mut Computer,
+    shared: Arc<Mutex<Shared>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl<'a> RunFuture<'a> {
+    // Runs `run` on a new worker thread, whose engine is given back to the `computer` once done.
+    pub(crate) fn new<F>(computer: &'a mut Computer, run: F) -> RunFuture<'a>
+    where
+        F: FnOnce() -> Outcome + Send + 'static,
+    {
+        let shared = Arc::new(Mutex::new(Shared {
+            outcome: None,
+            waker: None,
+        }));
+
+        let worker = {
+            let shared = Arc::clone(&shared);
+
+            thread::Builder::new()
+                .name("trident-run".to_string())
+                .spawn(move || {
+                    let outcome = run();
+
+                    let mut shared = shared.lock().expect("The state of the run is poisoned");
+                    shared.outcome = Some(outcome);
+                    if let Some(waker) = shared.waker.take() {
+                        waker.wake();
+                    }
+                })
+                .expect("Cannot spawn the worker thread of the run")
+        };
+
+        RunFuture {
+            computer,
+            shared,
+            worker: Some(worker),
+        }
+    }
+
+    // Gives the engine back to the computer, and returns the measurements.
+    fn finish(&mut self, (engine, measurements): Outcome) -> thread::Result<Measurements> {
+        if let Some(worker) = self.worker.take() {
+            // The worker has nothing left to do once the outcome is sent
+            let _ = worker.join();
+        }
+
+        self.computer.restore_engine(engine);
+        measurements
+    }
+}
+
+impl<'a> Future for RunFuture<'a> {
+    type Output = Measurements;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Measurements> {
+        let this = self.get_mut();
+
+        assert!(
+            this.worker.is_some(),
+            "The run is over, it's future cannot be polled any more"
+        );
+
+        let outcome = {
+            let mut shared = this.shared.lock().expect("The state of the run is poisoned");
+            match shared.outcome.take() {
+                Some(outcome) => outcome,
+                None => {
+                    shared.waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                },
+            }
+        };
+
+        match this.finish(outcome) {
+            Ok(measurements) => Poll::Ready(measurements),
+            Err(panic) => resume_unwind(panic),
+        }
+    }
+}
+
+impl<'a> Drop for RunFuture<'a> {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+
+            let outcome = self.shared.lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .outcome
+                .take();
+            if let Some((engine, _)) = outcome {
+                self.computer.restore_engine(engine);
+            }
+        }
+    }
+}
@@ -93,4 +93,39 @@ impl Gate {
         (a*b.conjugate() + c*d.conjugate()).approx_eq(c64::ZERO) &&
         approx_eq(b.norm_sqr() + d.norm_sqr(), 1f32)
     }
-}
\ No newline at end of file
+}
+
+//#################################################################################################
+//
+//                                          Pauli
+//
+//#################################################################################################
+
+/// A Pauli operator, whose eigenbasis a qbit can be measured in with
+/// `ProgramBuilder::measure_in_basis`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Pauli {
+    /// The basis `|+>`, `|->`, measured as 0 and 1.
+    X,
+    /// The basis `|+i>`, `|-i>`, measured as 0 and 1.
+    Y,
+    /// The computational basis `|0>`, `|1>`.
+    Z,
+}
+
+impl Pauli {
+    // Returns the gates rotating the eigenbasis of the operator onto the computational basis, in
+    // the order they are applied, with their names: H for X, and S† then H for Y.
+    pub(crate) fn basis_change(self) -> Vec<(&'static str, Gate)> {
+        let sqrt2inv = 2f32.sqrt().recip();
+
+        let h = unsafe { Gate::new_unchecked(sqrt2inv, sqrt2inv, sqrt2inv, -sqrt2inv) };
+        let s_dagger = unsafe { Gate::new_unchecked(1, 0, 0, -c64::I) };
+
+        match self {
+            Pauli::X => vec![("H", h)],
+            Pauli::Y => vec![("S†", s_dagger), ("H", h)],
+            Pauli::Z => Vec::new(),
+        }
+    }
+}
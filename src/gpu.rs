@@ -0,0 +1,643 @@
+use ocl::{Buffer, Device, Kernel, Platform, ProQue};
+
+use std::collections::HashMap;
+
+use crate::bitstring::BitString;
+use crate::complex::c64;
+use crate::computer::{Address, Backend};
+use crate::density::DensityMatrix;
+use crate::gates::Gate;
+use crate::noise::{scale, Channel, NoiseModel};
+use crate::program::Operation;
+use crate::random::{Philox, Stream};
+use crate::sampling::{AliasTable, Sampling};
+use crate::state::StateVector;
+
+//#################################################################################################
+//
+//                                         Gpu State
+//
+//#################################################################################################
+
+// The distribution of measured qbits, reduced in the main buffer by `prepare_distribution`.
+pub(crate) struct GpuDistribution {
+    // Number of measured qbits
+    measured: Address,
+    // Position of the state of qbits[i] in the sampled states
+    positions: Box<[usize]>,
+    // True if the qbits are measured in increasing order
+    ordered: bool,
+    // The alias table of the distribution, built when first needed
+    alias_table: Option<AliasTable>,
+}
+
+// The buffer and kernels needed to run noisy programs.
+struct NoiseKernels {
+    buffer: Buffer<c64>,
+    qbit_density: Kernel,
+    reduce_sum: Kernel,
+}
+
+// The buffers and kernels of the state vector and density matrix backends, simulated with OpenCL.
+pub(crate) struct GpuState {
+    size: Address,
+    backend: Backend,
+    main_buffer: Buffer<c64>,
+    measurements_buffer: Buffer<u64>,
+    // Number of samples drawn by every call to `do_measurements`
+    block: usize,
+    sampling: Sampling,
+    // Name of the OpenCL device
+    device: String,
+    apply_gate: Kernel,
+    apply_controlled_gate: Kernel,
+    calculate_probabilities: Kernel,
+    marginal_probabilities: Kernel,
+    move_marginal_probabilities: Kernel,
+    reduce_distribution: Kernel,
+    do_measurements: Kernel,
+    noise: Option<(NoiseModel, NoiseKernels)>,
+}
+
+impl GpuState {
+    // Initializes opencl, compiles the shader and allocates the buffers.
+    pub(crate) fn new(
+        size: Address,
+        backend: Backend,
+        noise_model: Option<NoiseModel>,
+        block: usize,
+        sampling: Sampling,
+        device: Option<(Platform, Device)>,
+    ) -> GpuState {
+        // The buffer is addressable, as checked by `ComputerBuilder::build`
+        let buffer_qbits = match backend {
+            Backend::DensityMatrix => 2 * size as usize,
+            _ => size as usize,
+        };
+        let dim = 1usize << size;
+        let buffer_len = 1usize << buffer_qbits;
+
+        let mut builder = ProQue::builder();
+        builder.src(include_str!("opencl/kernels.cl"))
+            .dims(buffer_len);
+        if let Some((platform, device)) = device {
+            builder.platform(platform)
+                .device(device);
+        }
+        let pro_que = builder.build()
+            .expect("Cannot build compute shader");
+
+        let device = pro_que.device().name().unwrap_or_default();
+
+        let main_buffer = pro_que.create_buffer()
+            .expect("Cannot create main buffer");
+
+        let measurements_buffer = pro_que.buffer_builder()
+            .len(block)
+            .build()
+            .expect("Cannot create measurements buffer");
+
+        let apply_gate = pro_que.kernel_builder("apply_gate")
+            .arg(&main_buffer)
+            .arg(0u8)
+            .arg(c64::ZERO)
+            .arg(c64::ZERO)
+            .arg(c64::ZERO)
+            .arg(c64::ZERO)
+            .global_work_size(buffer_len >> 1)
+            .build()
+            .expect("Cannot build kernel `apply_gate`");
+
+        let apply_controlled_gate = pro_que.kernel_builder("apply_controlled_gate")
+            .arg(&main_buffer)
+            .arg(0u8)
+            .arg(c64::ZERO)
+            .arg(c64::ZERO)
+            .arg(c64::ZERO)
+            .arg(c64::ZERO)
+            .arg(0u8)
+            .global_work_size(buffer_len >> 1)
+            .build()
+            .expect("Cannot build kernel `apply_controlled_gate`");
+
+        let calculate_probabilities = match backend {
+            Backend::DensityMatrix => pro_que.kernel_builder("calculate_diagonal_probabilities")
+                .arg(&main_buffer)
+                .arg(size)
+                .global_work_size(dim)
+                .build()
+                .expect("Cannot build kernel `calculate_diagonal_probabilities`"),
+            _ => pro_que.kernel_builder("calculate_probabilities")
+                .arg(&main_buffer)
+                .global_work_size(dim)
+                .build()
+                .expect("Cannot build kernel `calculate_probabilities`"),
+        };
+
+        let marginal_probabilities = pro_que.kernel_builder("marginal_probabilities")
+            .arg(&main_buffer)
+            .arg(0u64)
+            .arg(size)
+            .global_work_size(dim)
+            .build()
+            .expect("Cannot build kernel `marginal_probabilities`");
+
+        let move_marginal_probabilities = pro_que.kernel_builder("move_marginal_probabilities")
+            .arg(&main_buffer)
+            .global_work_size(dim)
+            .build()
+            .expect("Cannot build kernel `move_marginal_probabilities`");
+
+        let reduce_distribution = pro_que.kernel_builder("reduce_distribution")
+            .arg(&main_buffer)
+            .arg(0u8)
+            .global_work_size(dim >> 1)
+            .build()
+            .expect("Cannot build kernel `reduce_distribution`");
+
+        let do_measurements = pro_que.kernel_builder("do_measurements")
+            .arg(&main_buffer)
+            .arg(&measurements_buffer)
+            .arg(size)
+            .arg(0u64)
+            .arg(0u64)
+            .global_work_size(block)
+            .build()
+            .expect("Cannot build kernel `do_measurements`");
+
+        let noise = noise_model.map(|noise_model| {
+            // Holds dim/2 float4 during the calculation of reduced density matrices
+            let buffer = pro_que.buffer_builder()
+                .len(dim)
+                .build()
+                .expect("Cannot create noise buffer");
+
+            let qbit_density = pro_que.kernel_builder("qbit_density")
+                .arg(&main_buffer)
+                .arg(&buffer)
+                .arg(0u8)
+                .global_work_size(dim >> 1)
+                .build()
+                .expect("Cannot build kernel `qbit_density`");
+
+            let reduce_sum = pro_que.kernel_builder("reduce_sum")
+                .arg(&buffer)
+                .arg(0u64)
+                .global_work_size(1)
+                .build()
+                .expect("Cannot build kernel `reduce_sum`");
+
+            (noise_model, NoiseKernels {
+                buffer,
+                qbit_density,
+                reduce_sum,
+            })
+        });
+
+        GpuState {
+            size,
+            backend,
+            main_buffer,
+            measurements_buffer,
+            block,
+            sampling,
+            device,
+            apply_gate,
+            apply_controlled_gate,
+            calculate_probabilities,
+            marginal_probabilities,
+            move_marginal_probabilities,
+            reduce_distribution,
+            do_measurements,
+            noise,
+        }
+    }
+
+    // Returns the name of the OpenCL device.
+    pub(crate) fn device(&self) -> &str {
+        &self.device
+    }
+
+    // Returns the number of bytes allocated on the device.
+    pub(crate) fn memory_usage(&self) -> usize {
+        (self.main_buffer.len() + self.block) * 8
+    }
+
+    // Returns the number of trajectories to run, if there is a noise model.
+    pub(crate) fn trajectories(&self) -> Option<usize> {
+        self.noise.as_ref().map(|(model, _)| model.trajectories)
+    }
+
+    // Returns the amplitudes of the state obtained after applying the `operations`.
+    pub(crate) fn state_vector(&mut self, initial_state: &StateVector, operations: &[Operation]) -> Vec<c64> {
+        self.prepare_state(initial_state, operations, None);
+
+        let mut buffer = vec![c64::ZERO; self.main_buffer.len()];
+        self.main_buffer.read(&mut buffer)
+            .enq()
+            .expect("Cannot read from the main buffer");
+        buffer
+    }
+
+    // Returns the density matrix of the state obtained after applying the `operations`, averaged
+    // over the trajectories drawn from the `seed` if there is a noise model.
+    pub(crate) fn density_matrix(&mut self, initial_state: &StateVector, operations: &[Operation], seed: u64) -> DensityMatrix {
+        let read = |gpu: &GpuState| {
+            let mut buffer = vec![c64::ZERO; gpu.main_buffer.len()];
+            gpu.main_buffer.read(&mut buffer)
+                .enq()
+                .expect("Cannot read from the main buffer");
+            buffer
+        };
+
+        match self.backend {
+            Backend::DensityMatrix => {
+                self.prepare_state(initial_state, operations, None);
+                DensityMatrix::new(self.size, read(self).into())
+            },
+            _ => {
+                let states = match self.trajectories() {
+                    Some(trajectories) => {
+                        let prng = Philox::new(seed);
+
+                        (0..trajectories as u64)
+                            .map(|trajectory| {
+                                let mut prng = prng.substream(Stream::Noise, trajectory);
+                                self.prepare_state(initial_state, operations, Some(&mut prng));
+                                read(self)
+                            })
+                            .collect()
+                    },
+                    None => {
+                        self.prepare_state(initial_state, operations, None);
+                        vec![read(self)]
+                    },
+                };
+
+                DensityMatrix::from_state_vectors(self.size, &states)
+            },
+        }
+    }
+
+    // Initializes the main buffer with the `initial_state` and applies all of the `operations`. If
+    // a `prng` is given, the channels of the noise model are applied after each operation, which
+    // samples one trajectory.
+    pub(crate) fn prepare_state(
+        &mut self,
+        initial_state: &StateVector,
+        operations: &[Operation],
+        mut prng: Option<&mut Philox>,
+    ) {
+        // Initialization of amplitudes buffer: |ψ> for a state vector, |ψ><ψ| for a
+        // density matrix
+        match initial_state.basis_state() {
+            // Only one amplitude is written
+            Some((state, amplitude)) => {
+                // The state vector can't have more than 64 qbits
+                let state = state.to_u64().unwrap() as usize;
+
+                let (index, value) = match self.backend {
+                    Backend::DensityMatrix => (state * ((1 << self.size) + 1), c64::ONE),
+                    _ => (state, amplitude),
+                };
+
+                self.main_buffer.cmd()
+                    .fill(c64::ZERO, None)
+                    .enq()
+                    .expect("Cannot write to the main buffer");
+                self.main_buffer.write(&[value][..])
+                    .offset(index)
+                    .enq()
+                    .expect("Cannot write to the main buffer");
+            },
+            None => {
+                let vector = initial_state.dense();
+
+                let data = match self.backend {
+                    Backend::DensityMatrix => vector.iter()
+                        .flat_map(|col| vector.iter().map(move |row| *row * col.conjugate()))
+                        .collect(),
+                    _ => vector,
+                };
+
+                self.main_buffer.write(&data)
+                    .enq()
+                    .expect("Cannot write to the main buffer");
+            },
+        }
+
+        // Apply gates
+        for operation in operations.iter() {
+            let gate = operation.gate;
+
+            match self.backend {
+                // ρ is stored column major, ρ[r][c] being at index r + c*2^n: U acts on
+                // the lower qbits (rows) and U* on the upper qbits (columns)
+                Backend::DensityMatrix => {
+                    let conjugate = unsafe {
+                        Gate::new_unchecked(
+                            gate.u00.conjugate(),
+                            gate.u01.conjugate(),
+                            gate.u10.conjugate(),
+                            gate.u11.conjugate(),
+                        )
+                    };
+                    self.apply_gate(gate, operation.target, operation.control);
+                    self.apply_gate(
+                        conjugate,
+                        operation.target + self.size,
+                        operation.control.map(|c| c + self.size),
+                    );
+                },
+                _ => {
+                    self.apply_gate(gate, operation.target, operation.control);
+                },
+            }
+
+            if let (Some(prng), Some((model, kernels))) = (prng.as_mut(), self.noise.as_ref()) {
+                let qbits = std::iter::once(operation.target).chain(operation.control);
+
+                for qbit in qbits {
+                    for channel in model.channels(operation.gate_name, qbit) {
+                        self.apply_channel(kernels, channel, qbit, prng);
+                    }
+                }
+            }
+        }
+    }
+
+    // Applies the `gate` to the `target` qbit of the main buffer.
+    fn apply_gate(&self, gate: Gate, target: Address, control: Option<Address>) {
+        let kernel = if let Some(control) = control {
+            let kernel = &self.apply_controlled_gate;
+            kernel.set_arg(6, control).unwrap();
+            kernel
+        } else {
+            &self.apply_gate
+        };
+
+        kernel.set_arg(1, target).unwrap();
+
+        kernel.set_arg(2, gate.u00).unwrap();
+        kernel.set_arg(3, gate.u01).unwrap();
+        kernel.set_arg(4, gate.u10).unwrap();
+        kernel.set_arg(5, gate.u11).unwrap();
+
+        unsafe {
+            kernel.enq()
+                .expect("Cannot call kernel `apply_gate` or `apply_gate_controlled`");
+        }
+    }
+
+    // Applies one of the Kraus operators of the `channel` to the `qbit`, chosen randomly according
+    // to it's probability, and renormalizes the state.
+    fn apply_channel(&self, kernels: &NoiseKernels, channel: &Channel, qbit: Address, prng: &mut Philox) {
+        if channel.kraus.len() == 1 {
+            self.apply_gate(channel.kraus[0], qbit, None);
+            return;
+        }
+
+        // The probabilities of mixed unitary channels don't depend on the state
+        let probabilities = if channel.mixed_unitary {
+            channel.probabilities(0.5, 0.5, c64::ZERO)
+        } else {
+            let [diagonal, r01] = self.qbit_density(kernels, qbit);
+            channel.probabilities(diagonal.re(), diagonal.im(), r01)
+        };
+
+        // Drawn in double precision, and never among the operators of null probability, whose
+        // renormalization would be infinite
+        let total = probabilities.iter().fold(0.0, |total, &p| total + p.max(0.0) as f64);
+        let rand = prng.next_f64() * total;
+
+        let mut chosen = probabilities.iter()
+            .rposition(|&p| p > 0.0)
+            .expect("The channel has no Kraus operator of nonzero probability");
+        let mut cumulative = 0.0;
+
+        for (i, &p) in probabilities.iter().enumerate().filter(|(_, &p)| p > 0.0) {
+            cumulative += p as f64;
+            if rand < cumulative {
+                chosen = i;
+                break;
+            }
+        }
+
+        let gate = scale(&channel.kraus[chosen], probabilities[chosen].sqrt().recip());
+        self.apply_gate(gate, qbit, None);
+    }
+
+    // Returns the reduced density matrix [[r00, r01], [r01*, r11]] of the `qbit`, as
+    // [c64(r00, r11), r01].
+    fn qbit_density(&self, kernels: &NoiseKernels, qbit: Address) -> [c64; 2] {
+        kernels.qbit_density.set_arg(2, qbit).unwrap();
+
+        unsafe {
+            kernels.qbit_density.enq()
+                .expect("Cannot call kernel `qbit_density`");
+        }
+
+        let mut worksize: usize = 1 << (self.size - 1);
+
+        while worksize > 1 {
+            worksize >>= 1;
+
+            kernels.reduce_sum.set_arg(1, worksize as u64).unwrap();
+
+            unsafe {
+                kernels.reduce_sum.cmd()
+                    .global_work_size(worksize)
+                    .enq()
+                    .expect("Cannot call kernel `reduce_sum`");
+            }
+        }
+
+        let mut result = [c64::ZERO; 2];
+        kernels.buffer.read(&mut result[..])
+            .len(2)
+            .enq()
+            .expect("Cannot read from the noise buffer");
+
+        result
+    }
+
+    // Performs `samples` measurements of the `qbits` of the state held in the main buffer, the
+    // shots #first to #first+samples-1, and adds them to the `results`, after passing them through
+    // `readout`. Bit #i of the results is the state of qbits[i].
+    pub(crate) fn sample<F>(
+        &mut self,
+        samples: usize,
+        first: u64,
+        qbits: &[Address],
+        prng: &Philox,
+        results: &mut HashMap<BitString, usize>,
+        readout: F,
+    )
+    where
+        F: FnMut(BitString) -> BitString,
+    {
+        let mut distribution = self.prepare_distribution(qbits);
+        self.sample_distribution(&mut distribution, samples, first, prng, results, readout);
+    }
+
+    // Computes the distribution of the `qbits` of the state held in the main buffer, and reduces it
+    // in place so that it can be sampled by `sample_distribution`.
+    pub(crate) fn prepare_distribution(&mut self, qbits: &[Address]) -> GpuDistribution {
+        // Calculate the probabilities vector
+        unsafe {
+            self.calculate_probabilities.enq()
+                .expect("Cannot call kernel `calculate_probabilities`");
+        }
+
+        // The measured qbits in increasing order, the probabilities being marginalized over the
+        // other ones
+        let mut sorted = qbits.to_vec();
+        sorted.sort_unstable();
+        let measured = sorted.len() as Address;
+
+        if measured != self.size {
+            let mask = sorted.iter().fold(0u64, |mask, &qbit| mask | 1 << qbit);
+            self.marginal_probabilities.set_arg(1, mask).unwrap();
+
+            unsafe {
+                self.marginal_probabilities.cmd()
+                    .global_work_size(1usize << measured)
+                    .enq()
+                    .expect("Cannot call kernel `marginal_probabilities`");
+
+                self.move_marginal_probabilities.cmd()
+                    .global_work_size(1usize << measured)
+                    .enq()
+                    .expect("Cannot call kernel `move_marginal_probabilities`");
+            }
+        }
+
+        // Position of the state of qbits[i] in the sampled states
+        let positions: Box<[usize]> = qbits.iter()
+            .map(|qbit| sorted.binary_search(qbit).unwrap())
+            .collect();
+        let ordered = positions.iter().enumerate().all(|(i, &position)| i == position);
+
+        // Reduce probabilities
+        {
+            let mut worksize: usize = 1 << (measured - 1);
+
+            for pass in 1..measured {
+                self.reduce_distribution.set_default_global_work_size(worksize.into());
+                self.reduce_distribution.set_arg(1, pass).unwrap();
+
+                unsafe {
+                    self.reduce_distribution.enq()
+                        .expect("Cannot call kernel `reduce_distribution`");
+                }
+
+                worksize >>= 1;
+            }
+        }
+
+        GpuDistribution {
+            measured,
+            positions,
+            ordered,
+            alias_table: None,
+        }
+    }
+
+    // Returns the probability of the measured `state` of the `distribution` held in the main buffer.
+    pub(crate) fn distribution_probability(&self, distribution: &GpuDistribution, state: &BitString) -> f64 {
+        // The probabilities of the measured states, sorted by qbit, are left in the real parts of
+        // the buffer by the reduction
+        let index = distribution.positions.iter()
+            .enumerate()
+            .filter(|&(i, _)| state.get(i))
+            .fold(0usize, |index, (_, &position)| index | 1 << position);
+
+        let mut probability = [c64::ZERO];
+        self.main_buffer.read(&mut probability[..])
+            .offset(index)
+            .len(1)
+            .enq()
+            .expect("Cannot read from the main buffer");
+
+        probability[0].re() as f64
+    }
+
+    // Performs `samples` measurements of the `distribution` held in the main buffer, the shots
+    // #first to #first+samples-1, and adds them to the `results`, after passing them through
+    // `readout`. The shot #i is drawn from the substream #i of the sampling stream of `prng`, so
+    // that the samples don't depend on the size of the blocks.
+    pub(crate) fn sample_distribution<F>(
+        &mut self,
+        distribution: &mut GpuDistribution,
+        samples: usize,
+        first: u64,
+        prng: &Philox,
+        results: &mut HashMap<BitString, usize>,
+        mut readout: F,
+    )
+    where
+        F: FnMut(BitString) -> BitString,
+    {
+        let measured = distribution.measured;
+        let states = 1usize << measured;
+
+        // Reads the state of the #i sample, in the order of the measured qbits
+        let (ordered, positions) = (distribution.ordered, &distribution.positions);
+        let state = |i: usize| {
+            let state = BitString::from_u64(i as u64, measured as usize);
+            if ordered {
+                state
+            } else {
+                state.select(positions)
+            }
+        };
+
+        if distribution.alias_table.is_some() || self.sampling.alias_table(states, samples) {
+            if distribution.alias_table.is_none() {
+                // The probabilities of the measured states are left in the real parts of the
+                // buffer by the reduction
+                let mut buffer = vec![c64::ZERO; states];
+                self.main_buffer.read(&mut buffer)
+                    .len(states)
+                    .enq()
+                    .expect("Cannot read from the main buffer");
+
+                let weights: Vec<f64> = buffer.iter().map(|p| p.re() as f64).collect();
+                distribution.alias_table = Some(AliasTable::new(&weights));
+            }
+
+            let alias_table = distribution.alias_table.as_ref().unwrap();
+            for shot in first..first + samples as u64 {
+                let i = alias_table.sample(&mut prng.substream(Stream::Sampling, shot));
+                *results.entry(readout(state(i))).or_insert(0) += 1;
+            }
+            return;
+        }
+
+        let mut buffer = vec![0; self.block];
+        let mut done = 0;
+
+        self.do_measurements.set_arg(2, measured).unwrap();
+        self.do_measurements.set_arg(3, prng.key()).unwrap();
+
+        while done != samples {
+            let measures = std::cmp::min(samples - done, self.block);
+
+            self.do_measurements.set_arg(4, first + done as u64).unwrap();
+            done += measures;
+
+            unsafe {
+                self.do_measurements.enq()
+                    .expect("Cannot call kernel `do_measurements`");
+            }
+
+            self.measurements_buffer.read(&mut buffer)
+                .enq()
+                .expect("Cannot read from buffer `measurements`");
+
+            for &i in buffer.iter().take(measures) {
+                *results.entry(readout(state(i as usize))).or_insert(0) += 1;
+            }
+        }
+    }
+}
@@ -14,14 +14,29 @@
 extern crate ocl;
 
 // Modules
+mod bitstring;
 mod complex;
 mod computer;
+mod density;
+mod device;
+mod future;
 mod gates;
+mod gpu;
+mod linalg;
 mod measure;
+mod mps;
+mod noise;
+mod pool;
+mod prepared;
 mod program;
 mod random;
-
-const MEASUREMENTS_BLOCK: usize = 1024;
+mod readout;
+mod register;
+mod sampling;
+mod sparse;
+mod stabilizer;
+mod state;
+mod statistics;
 
 #[inline]
 pub(crate) fn approx_eq(x: f32, y: f32) -> bool {
@@ -29,8 +44,22 @@ pub(crate) fn approx_eq(x: f32, y: f32) -> bool {
 }
 
 // Exports
+pub use bitstring::{BitOrder, BitString};
 pub use complex::c64;
-pub use computer::{Address, Computer, ComputerBuilder};
-pub use gates::Gate;
-pub use measure::Measurements;
-pub use program::{InstructionChain, Program, ProgramBuilder};
+pub use computer::{Address, Backend, Computer, ComputerBuilder};
+pub use density::DensityMatrix;
+pub use device::{DeviceInfo, DeviceKind, DeviceSelector};
+pub use future::RunFuture;
+pub use gates::{Gate, Pauli};
+pub use measure::{Accumulator, HistogramSort, Measurements, Shots, StateFormat};
+pub use mps::MatrixProductState;
+pub use noise::{Channel, NoiseModel};
+pub use pool::{ComputerPool, JobHandle, JobStatus};
+pub use prepared::PreparedState;
+pub use program::{InitialState, InstructionChain, Program, ProgramBuilder};
+pub use random::SeedSource;
+pub use readout::{ConfusionMatrix, Mitigation};
+pub use register::Register;
+pub use sampling::Sampling;
+pub use state::StateVector;
+pub use statistics::{ChiSquared, Interval};
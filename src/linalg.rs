@@ -0,0 +1,241 @@
+use crate::complex::{c128, c64};
+
+//#################################################################################################
+//
+//                                    Eigenvalue decomposition
+//
+//#################################################################################################
+
+// Returns the eigenvalues of the `dim`×`dim` hermitian matrix stored column major in `matrix`,
+// sorted in decreasing order.
+//
+// The hermitian matrix H = A + iB is embedded into the real symmetric matrix
+// [[A, -B], [B, A]], whose spectrum is the one of H with every eigenvalue doubled, which is then
+// diagonalized with the cyclic Jacobi method in double precision.
+pub(crate) fn hermitian_eigenvalues(dim: usize, matrix: &[c64]) -> Box<[f64]> {
+    let n = 2 * dim;
+    let mut m = vec![0f64; n * n];
+
+    for col in 0..dim {
+        for row in 0..dim {
+            let value = matrix[row + col * dim];
+            let (re, im) = (value.re() as f64, value.im() as f64);
+
+            m[row * n + col] = re;
+            m[(row + dim) * n + col + dim] = re;
+            m[(row + dim) * n + col] = im;
+            m[row * n + col + dim] = -im;
+        }
+    }
+
+    const MAX_SWEEPS: usize = 64;
+
+    for _ in 0..MAX_SWEEPS {
+        let off: f64 = (0..n)
+            .flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
+            .map(|(i, j)| m[i * n + j] * m[i * n + j])
+            .sum();
+
+        if off < 1e-22 {
+            break;
+        }
+
+        for p in 0..n {
+            for q in p+1..n {
+                let apq = m[p * n + q];
+                if apq.abs() < 1e-300 {
+                    continue;
+                }
+
+                let theta = (m[q * n + q] - m[p * n + p]) / (2.0 * apq);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let t = if theta == 0.0 { 1.0 } else { t };
+                let c = (t * t + 1.0).sqrt().recip();
+                let s = t * c;
+
+                for k in 0..n {
+                    let (mkp, mkq) = (m[k * n + p], m[k * n + q]);
+                    m[k * n + p] = c * mkp - s * mkq;
+                    m[k * n + q] = s * mkp + c * mkq;
+                }
+                for k in 0..n {
+                    let (mpk, mqk) = (m[p * n + k], m[q * n + k]);
+                    m[p * n + k] = c * mpk - s * mqk;
+                    m[q * n + k] = s * mpk + c * mqk;
+                }
+            }
+        }
+    }
+
+    let mut eigenvalues: Vec<f64> = (0..n).map(|i| m[i * n + i]).collect();
+    eigenvalues.sort_by(|a, b| b.partial_cmp(a).unwrap());
+
+    eigenvalues.into_iter()
+        .step_by(2)
+        .collect()
+}
+
+//#################################################################################################
+//
+//                                 Singular value decomposition
+//
+//#################################################################################################
+
+// The factors U, S and V of a singular value decomposition.
+pub(crate) type Svd = (Box<[c128]>, Box<[f64]>, Box<[c128]>);
+
+// Returns the singular value decomposition A = U diag(S) V† of the `rows`×`cols` matrix stored
+// column major in `matrix`, as the triple (U, S, V). With k = min(rows, cols), U is `rows`×k and
+// V is `cols`×k, both column major, and S is sorted in decreasing order.
+//
+// Uses the one-sided Jacobi method of Hestenes in double precision, which orthogonalizes the
+// columns of A by plane rotations.
+pub(crate) fn singular_value_decomposition(rows: usize, cols: usize, matrix: &[c128]) -> Svd {
+    // Works on A† when it has less columns: A = (V S U†)†
+    if cols > rows {
+        let adjoint: Vec<c128> = (0..rows)
+            .flat_map(|row| (0..cols).map(move |col| matrix[row + col * rows].conjugate()))
+            .collect();
+        let (u, s, v) = singular_value_decomposition(cols, rows, &adjoint);
+        return (v, s, u);
+    }
+
+    let mut a = matrix.to_vec();
+    let mut v = vec![c128::ZERO; cols * cols];
+    for i in 0..cols {
+        v[i + i * cols] = c128::ONE;
+    }
+
+    // Rotates the columns p and q of the column major matrix `m` of `n` rows
+    let rotate = |m: &mut [c128], n: usize, p: usize, q: usize, c: f64, s: f64, phase: c128| {
+        for k in 0..n {
+            let (x, y) = (m[k + p * n], m[k + q * n]);
+            m[k + p * n] = x.scale(c) - (phase.conjugate() * y).scale(s);
+            m[k + q * n] = (phase * x).scale(s) + y.scale(c);
+        }
+    };
+
+    const MAX_SWEEPS: usize = 64;
+
+    for _ in 0..MAX_SWEEPS {
+        let mut rotated = false;
+
+        for p in 0..cols {
+            for q in p+1..cols {
+                let (mut alpha, mut beta, mut gamma) = (0.0, 0.0, c128::ZERO);
+                for k in 0..rows {
+                    let (x, y) = (a[k + p * rows], a[k + q * rows]);
+                    alpha += x.norm_sqr();
+                    beta += y.norm_sqr();
+                    gamma = gamma + x.conjugate() * y;
+                }
+
+                let g = gamma.norm();
+                if g < 1e-300 || g <= 1e-15 * (alpha * beta).sqrt() {
+                    continue;
+                }
+                rotated = true;
+
+                let zeta = (beta - alpha) / (2.0 * g);
+                let t = zeta.signum() / (zeta.abs() + (zeta * zeta + 1.0).sqrt());
+                let c = (t * t + 1.0).sqrt().recip();
+                let phase = gamma.scale(g.recip());
+
+                rotate(&mut a, rows, p, q, c, c * t, phase);
+                rotate(&mut v, cols, p, q, c, c * t, phase);
+            }
+        }
+
+        if !rotated {
+            break;
+        }
+    }
+
+    let norms: Vec<f64> = (0..cols)
+        .map(|j| a[j * rows..(j + 1) * rows].iter().map(|x| x.norm_sqr()).sum::<f64>().sqrt())
+        .collect();
+    let mut order: Vec<usize> = (0..cols).collect();
+    order.sort_by(|&i, &j| norms[j].partial_cmp(&norms[i]).unwrap());
+
+    let u = order.iter()
+        .flat_map(|&j| {
+            let inverse = if norms[j] > 0.0 { norms[j].recip() } else { 0.0 };
+            a[j * rows..(j + 1) * rows].iter().map(move |x| x.scale(inverse))
+        })
+        .collect();
+    let s = order.iter().map(|&j| norms[j]).collect();
+    let v = order.iter()
+        .flat_map(|&j| v[j * cols..(j + 1) * cols].iter().copied())
+        .collect();
+
+    (u, s, v)
+}
+
+//#################################################################################################
+//
+//                                           Tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Checks that the decomposition of the `rows`×`cols` `matrix` is a product of orthonormal
+    // factors and of decreasing singular values giving back the matrix.
+    fn check_svd(rows: usize, cols: usize, matrix: &[c128]) -> Box<[f64]> {
+        let (u, s, v) = singular_value_decomposition(rows, cols, matrix);
+        let k = rows.min(cols);
+
+        assert_eq!((u.len(), s.len(), v.len()), (rows * k, k, cols * k));
+        assert!(s.windows(2).all(|w| w[0] >= w[1]), "Unsorted singular values {:?}", s);
+
+        for row in 0..rows {
+            for col in 0..cols {
+                let x = (0..k).fold(c128::ZERO, |acc, i| acc + u[row + rows * i].scale(s[i]) * v[col + cols * i].conjugate());
+                assert!((x - matrix[row + rows * col]).norm() < 1e-12, "U S V† differs from A at ({}, {})", row, col);
+            }
+        }
+
+        // Only the columns of nonzero singular values are orthonormal
+        for (factor, n) in [(&u, rows), (&v, cols)].iter() {
+            for i in (0..k).filter(|&i| s[i] > 1e-12) {
+                for j in (0..k).filter(|&j| s[j] > 1e-12) {
+                    let dot = (0..*n).fold(c128::ZERO, |acc, m| acc + factor[m + n * i].conjugate() * factor[m + n * j]);
+                    let expected = if i == j { c128::ONE } else { c128::ZERO };
+                    assert!((dot - expected).norm() < 1e-12, "The factors are not orthonormal");
+                }
+            }
+        }
+
+        s
+    }
+
+    #[test]
+    fn svd_known_values() {
+        // diag(2, 3), column major
+        let s = check_svd(2, 2, &[c128(2.0, 0.0), c128::ZERO, c128::ZERO, c128(3.0, 0.0)]);
+        assert!((s[0] - 3.0).abs() < 1e-12 && (s[1] - 2.0).abs() < 1e-12);
+
+        // The rank one matrix (1, i)ᵀ (1, 1), of singular value 2
+        let s = check_svd(2, 2, &[c128(1.0, 0.0), c128(0.0, 1.0), c128(1.0, 0.0), c128(0.0, 1.0)]);
+        assert!((s[0] - 2.0).abs() < 1e-12 && s[1].abs() < 1e-12);
+    }
+
+    #[test]
+    fn svd_rectangular() {
+        let matrix: Vec<c128> = (0..12)
+            .map(|i| c128((i as f64 * 0.7).sin(), (i as f64 * 1.3).cos()))
+            .collect();
+
+        // Tall, and wide through the adjoint
+        let tall = check_svd(4, 3, &matrix);
+        let wide = check_svd(3, 4, &matrix);
+        assert_eq!((tall.len(), wide.len()), (3, 3));
+
+        // The Frobenius norm is the norm of the singular values
+        let norm: f64 = matrix.iter().map(|x| x.norm_sqr()).sum();
+        assert!((tall.iter().map(|s| s * s).sum::<f64>() - norm).abs() < 1e-12);
+        assert!((wide.iter().map(|s| s * s).sum::<f64>() - norm).abs() < 1e-12);
+    }
+}
@@ -1,9 +1,15 @@
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::cmp::{Eq, Ord, Ordering};
 use std::fmt;
+use std::ops::{Add, AddAssign};
 use std::time::Duration;
 
+use crate::bitstring::{BitOrder, BitString};
 use crate::computer::Address;
+use crate::gates::Pauli;
+use crate::readout::{apply_tensor, project_simplex, ConfusionMatrix, Mitigation};
+use crate::register::Register;
+use crate::statistics::{chi_squared_survival, hellinger, total_variation, ChiSquared, Interval};
 
 //#################################################################################################
 //
@@ -11,10 +17,121 @@ use crate::computer::Address;
 //
 //#################################################################################################
 
-#[derive(PartialEq, PartialOrd)]
+/// The way measured states are written by the exports of `Measurements`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StateFormat {
+    /// One digit per qbit, such as `0110`.
+    Binary,
+    /// The value of the state as an unsigned integer, such as `6`.
+    Decimal,
+    /// The value of the state as an unsigned integer in lowercase hexadecimal, padded to one digit
+    /// per 4 qbits, such as `06`.
+    Hexadecimal,
+}
+
+impl StateFormat {
+    // Writes the `state` in this format, it's bits being read in `order`.
+    pub(crate) fn format(self, state: &BitString, order: BitOrder) -> String {
+        let len = state.len();
+
+        // The bits of the value, least significant first
+        let value = match order {
+            BitOrder::LittleEndian => state.clone(),
+            BitOrder::BigEndian => state.select(&(0..len).rev().collect::<Vec<_>>()),
+        };
+
+        match self {
+            StateFormat::Binary => state.display(order).to_string(),
+            StateFormat::Decimal => {
+                // Repeated divisions by 10^19, the largest power of 10 fitting in a word
+                const CHUNK: u128 = 10_000_000_000_000_000_000;
+
+                let mut words = value.words().to_vec();
+                let mut chunks = Vec::new();
+
+                while words.iter().any(|&word| word != 0) {
+                    let mut remainder = 0u128;
+                    for word in words.iter_mut().rev() {
+                        let dividend = remainder << 64 | *word as u128;
+                        *word = (dividend / CHUNK) as u64;
+                        remainder = dividend % CHUNK;
+                    }
+                    chunks.push(remainder as u64);
+                }
+
+                match chunks.split_last() {
+                    None => "0".to_string(),
+                    Some((first, rest)) => rest.iter()
+                        .rev()
+                        .fold(first.to_string(), |result, chunk| format!("{}{:019}", result, chunk)),
+                }
+            },
+            StateFormat::Hexadecimal => {
+                let digits = len.div_ceil(4).max(1);
+                (0..digits)
+                    .rev()
+                    .map(|digit| {
+                        let nibble = (0..4)
+                            .map(|i| 4 * digit + i)
+                            .filter(|&bit| bit < len && value.get(bit))
+                            .fold(0, |nibble, bit| nibble | 1 << (bit % 4));
+                        std::char::from_digit(nibble, 16).unwrap()
+                    })
+                    .collect()
+            },
+        }
+    }
+}
+
+/// The order of the bars of `Measurements::histogram`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HistogramSort {
+    /// From most frequent to least frequent.
+    Count,
+    /// By increasing state, as written.
+    State,
+}
+
+// Renders the `entries`, labels and counts out of `samples`, as bars of at most `width` characters,
+// the longest being the one of the most frequent entry. `hidden` entries are summarized at the end.
+fn histogram_bars(entries: &[(String, usize)], hidden: usize, samples: usize, width: usize) -> String {
+    const EIGHTHS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+    let label_width = entries.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+    let max = entries.iter().map(|&(_, count)| count).max().unwrap_or(0).max(1);
+
+    let mut result = String::new();
+    for (label, count) in entries {
+        // Length of the bar in eighths of a character
+        let eighths = (count * width * 8 + max / 2) / max;
+
+        let mut bar = "█".repeat(eighths / 8);
+        let partial = eighths % 8;
+        if partial != 0 {
+            bar.push(EIGHTHS[partial]);
+        }
+
+        result.push_str(&format!(
+            "{:>label_width$} {:<width$} {:5.2}%\n",
+            label,
+            bar,
+            *count as f64 / samples as f64 * 100.0,
+            label_width = label_width,
+            width = width,
+        ));
+    }
+
+    if hidden != 0 {
+        result.push_str(&format!("{:>label_width$} and {} more...\n", "", hidden, label_width = label_width));
+    }
+
+    result
+}
+
+#[derive(Clone, PartialEq)]
 struct Measurement {
     count: usize,
-    state: u64,
+    state: BitString,
     frequency: f64,
 }
 
@@ -30,45 +147,165 @@ impl Ord for Measurement {
     }
 }
 
+impl PartialOrd for Measurement {
+    #[inline]
+    fn partial_cmp(&self, other: &Measurement) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+//#################################################################################################
+//
+//                                           Shots
+//
+//#################################################################################################
+
+/// The outcomes of the shots of a run, in the order they were sampled, kept when the program was
+/// built with `ProgramBuilder::keep_shots`. Every outcome is packed in the fewest 64 bits words.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shots {
+    width: usize,
+    limit: usize,
+    len: usize,
+    words: Vec<u64>,
+}
+
+impl Shots {
+    // Creates an empty list of shots of `width` bits, keeping at most the `limit` first ones, with
+    // room for `capacity` shots. The limit also bounds the shots appended by merges, so it is not
+    // capped by the samples of a single run.
+    pub(crate) fn new(width: usize, limit: usize, capacity: usize) -> Shots {
+        Shots {
+            width,
+            limit,
+            len: 0,
+            words: Vec::with_capacity(capacity * width.div_ceil(64).max(1)),
+        }
+    }
+
+    // Number of words of every shot.
+    fn stride(&self) -> usize {
+        self.width.div_ceil(64).max(1)
+    }
+
+    // Adds the outcome of the next shot, unless the limit was reached.
+    pub(crate) fn push(&mut self, state: &BitString) {
+        if self.len < self.limit {
+            self.words.extend_from_slice(state.words());
+            self.len += 1;
+        }
+    }
+
+    // Adds the shots of `other` after these ones, up to the limit.
+    pub(crate) fn append(&mut self, other: &Shots) {
+        let count = other.len.min(self.limit - self.len);
+        self.words.extend_from_slice(&other.words[..count * other.stride()]);
+        self.len += count;
+    }
+
+    /// Returns the number of shots kept.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if no shots were kept.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the outcome of the shot #i.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if `i` is not less than the number of shots kept.
+    pub fn get(&self, i: usize) -> BitString {
+        assert!(
+            i < self.len,
+            "Shot #{} is out of the {} kept shots",
+            i,
+            self.len,
+        );
+
+        let stride = self.stride();
+        BitString::from_words(&self.words[i * stride..(i + 1) * stride], self.width)
+    }
+
+    /// Returns an iterator over the outcomes of the shots, in the order they were sampled.
+    pub fn iter(&self) -> impl Iterator<Item = BitString> + '_ {
+        self.words.chunks(self.stride()).map(move |words| BitString::from_words(words, self.width))
+    }
+}
+
+// Sorts the measured states from most frequent to least frequent, with their frequency.
+fn sorted_measures(counts: HashMap<BitString, usize>, samples: usize) -> BTreeSet<Measurement> {
+    counts.into_iter()
+        .map(|(state, count)| Measurement {
+            count,
+            state,
+            frequency: count as f64 / samples as f64,
+        })
+        .collect()
+}
+
 /// Holds every information and results of a previous computation.
+#[derive(Clone)]
 pub struct Measurements {
     duration: Duration,
     size: Address,
+    qbits: Box<[Address]>,
+    bases: Box<[Pauli]>,
     samples: usize,
     measures: BTreeSet<Measurement>,
+    registers: Box<[Register]>,
+    bit_order: BitOrder,
     min_percentile: Option<f64>,
     max_display: Option<usize>,
+    histogram_sort: HistogramSort,
+    histogram_by_register: bool,
+    shots: Option<Shots>,
+    seed: Option<u64>,
 }
 
 impl Measurements {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         duration: Duration, 
-        size: Address, 
+        qbits: Box<[Address]>, 
+        bases: Box<[Pauli]>, 
         samples: usize, 
-        measures: HashMap<u64, usize>
+        measures: HashMap<BitString, usize>,
+        registers: Box<[Register]>,
+        bit_order: BitOrder,
+        shots: Option<Shots>,
+        seed: u64,
     ) -> Measurements {
-        let measures = {
-            let mut res = BTreeSet::new();
-            for (state, count) in measures {
-                res.insert(Measurement {
-                    count, 
-                    state, 
-                    frequency: count as f64 / samples as f64,
-                });
-            }
-            res
-        };
+        let measures = sorted_measures(measures, samples);
+
+        let size = qbits.len() as Address;
 
         let min_percentile = None;
         let max_display = Some(25);
 
+        let histogram_sort = HistogramSort::Count;
+        let histogram_by_register = false;
+
+        let seed = Some(seed);
+
         Measurements {
             duration,
             size,
+            qbits,
+            bases,
             samples,
             measures,
+            registers,
+            bit_order,
             max_display, 
             min_percentile,
+            histogram_sort,
+            histogram_by_register,
+            shots,
+            seed,
         }
     }
 
@@ -77,16 +314,505 @@ impl Measurements {
         self.duration
     }
 
+    /// Returns the seed of the pseudo-random number generator used for the measurements, which
+    /// recreates them when passed to `Computer::run` or `PreparedState::sample`, or `None` if they
+    /// merge runs of different seeds.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Returns the number of qbits that were measured.
+    pub fn size(&self) -> Address {
+        self.size
+    }
+
+    /// Returns the addresses of the measured qbits: bit #i of the measured states is the state of
+    /// the qbit `qbits()[i]`.
+    pub fn qbits(&self) -> &[Address] {
+        &self.qbits
+    }
+
+    /// Returns the basis in which each qbit was measured: `bases()[i]` is the one of the qbit
+    /// `qbits()[i]`.
+    pub fn bases(&self) -> &[Pauli] {
+        &self.bases
+    }
+
+    /// Returns the total number of samples.
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    // Returns the measured states and the number of times they were measured.
+    pub(crate) fn counts(&self) -> impl Iterator<Item = (&BitString, usize)> + '_ {
+        self.measures.iter().map(|m| (&m.state, m.count))
+    }
+
     /// Returns the `n` most frequent states measured, and their frequency of apparition,
     /// from most frequent to least frequent.
     /// If they was less than `n` different states measured, returns all of them.
-    pub fn n_most(&self, n: usize) -> Box<[u64]> {
+    pub fn n_most(&self, n: usize) -> Box<[BitString]> {
         self.measures.iter()
-            .map(|pair| pair.state)
+            .map(|pair| pair.state.clone())
             .take(n)
             .collect()
     }
 
+    /// Returns the bit order in which the states are displayed and decoded by default.
+    pub fn bit_order(&self) -> BitOrder {
+        self.bit_order
+    }
+
+    /// Returns the registers declared by the program.
+    pub fn registers(&self) -> &[Register] {
+        &self.registers
+    }
+
+    /// Returns the register of the program named `name`, if any.
+    pub fn register(&self, name: &str) -> Option<&Register> {
+        self.registers.iter().find(|register| register.name() == name)
+    }
+
+    /// Decodes the given `qbits` of every measured state as an unsigned integer, read in the given
+    /// `order` or in the bit order of the computer if `None`, and returns the number of times each
+    /// value was measured, sorted by value.
+    /// 
+    /// `qbits` can be a register, such as `&register`, a range or any list of addresses.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if there are more than 64 qbits, or if one of them was not measured.
+    pub fn integer_counts<Q, O>(&self, qbits: Q, order: O) -> BTreeMap<u64, usize>
+    where
+        Q: IntoIterator<Item = Address>,
+        O: Into<Option<BitOrder>>,
+    {
+        let bits = self.bits(qbits);
+        let order = order.into().unwrap_or(self.bit_order);
+
+        let mut result = BTreeMap::new();
+        for measure in self.measures.iter() {
+            *result.entry(order.decode(&measure.state, &bits)).or_insert(0) += measure.count;
+        }
+        result
+    }
+
+    /// Decodes the value of the `register` in every measured state, see `Register::decode`, in the
+    /// given `order` or in the bit order of the computer if `None`, and returns the number of times
+    /// each value was measured, sorted by value.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if the register is wider than 64 qbits, or if one of it's qbits was
+    /// not measured.
+    pub fn register_counts<O>(&self, register: &Register, order: O) -> BTreeMap<u64, usize>
+    where
+        O: Into<Option<BitOrder>>,
+    {
+        self.integer_counts(register, order)
+    }
+
+    /// Returns the histogram of the values of every register declared by the program, see
+    /// `Measurements::register_counts`, in the order of declaration.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if a register is wider than 64 qbits, or if one of it's qbits was not
+    /// measured.
+    pub fn register_histograms<O>(&self, order: O) -> Box<[(&Register, BTreeMap<u64, usize>)]>
+    where
+        O: Into<Option<BitOrder>>,
+    {
+        let order = order.into();
+        self.registers.iter()
+            .map(|register| (register, self.register_counts(register, order)))
+            .collect()
+    }
+
+    /// Returns the marginal distribution of the given `qbits`: the number of times each state of
+    /// these qbits was measured, whatever the state of the others. Bit #i of the returned states is
+    /// the state of the i-th given qbit.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if one of the qbits was not measured.
+    pub fn marginal<Q>(&self, qbits: Q) -> BTreeMap<BitString, usize>
+    where
+        Q: IntoIterator<Item = Address>,
+    {
+        let bits = self.bits(qbits);
+
+        let mut result = BTreeMap::new();
+        for measure in self.measures.iter() {
+            *result.entry(measure.state.select(&bits)).or_insert(0) += measure.count;
+        }
+        result
+    }
+
+    /// Returns the outcomes of the shots in the order they were sampled, if the program was built
+    /// with `ProgramBuilder::keep_shots`.
+    pub fn shots(&self) -> Option<&Shots> {
+        self.shots.as_ref()
+    }
+
+    /// Decodes the given `qbits` of the outcome of every kept shot as an unsigned integer, read in
+    /// the given `order` or in the bit order of the computer if `None`, in the order of the shots.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if the shots were not kept, if there are more than 64 qbits, or if
+    /// one of them was not measured.
+    pub fn integer_shots<Q, O>(&self, qbits: Q, order: O) -> Box<[u64]>
+    where
+        Q: IntoIterator<Item = Address>,
+        O: Into<Option<BitOrder>>,
+    {
+        let shots = self.shots.as_ref()
+            .unwrap_or_else(|| panic!("The shots were not kept, see `ProgramBuilder::keep_shots`"));
+        let bits = self.bits(qbits);
+        let order = order.into().unwrap_or(self.bit_order);
+
+        shots.iter()
+            .map(|state| order.decode(&state, &bits))
+            .collect()
+    }
+
+    /// Decodes the value of the `register` in the outcome of every kept shot, see
+    /// `Register::decode`, in the given `order` or in the bit order of the computer if `None`, in
+    /// the order of the shots.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if the shots were not kept, if the register is wider than 64 qbits,
+    /// or if one of it's qbits was not measured.
+    pub fn register_shots<O>(&self, register: &Register, order: O) -> Box<[u64]>
+    where
+        O: Into<Option<BitOrder>>,
+    {
+        self.integer_shots(register, order)
+    }
+
+    // Checks that the qbits were measured and returns their positions in the measured states.
+    fn bits<Q>(&self, qbits: Q) -> Vec<usize>
+    where
+        Q: IntoIterator<Item = Address>,
+    {
+        qbits.into_iter()
+            .map(|qbit| {
+                self.qbits.iter()
+                    .position(|&measured| measured == qbit)
+                    .unwrap_or_else(|| panic!("Qbit #{} was not measured", qbit))
+            })
+            .collect()
+    }
+
+    /// Corrects the measured frequencies from readout errors and returns the mitigated probability of
+    /// each state, using the given `method`. `confusion[i]` is the confusion matrix of the qbit
+    /// `qbits()[i]`.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if there isn't exactly one confusion matrix per measured qbit, or if
+    /// one of them is not invertible.
+    pub fn mitigate_readout(&self, confusion: &[ConfusionMatrix], method: Mitigation) -> HashMap<BitString, f64> {
+        assert!(
+            confusion.len() == self.size as usize,
+            "Expected {} confusion matrices, one per qbit, but got {}",
+            self.size,
+            confusion.len(),
+        );
+
+        let frequencies = self.frequencies();
+
+        let inverses: Box<[_]> = confusion.iter().map(ConfusionMatrix::inverse).collect();
+        let mut result = apply_tensor(frequencies.clone(), &inverses);
+
+        if method == Mitigation::LeastSquares {
+            // Projected gradient descent of ||Cp - f||², starting from the projection of the
+            // inverted frequencies
+            let matrices: Box<[_]> = confusion.iter().map(ConfusionMatrix::matrix).collect();
+            let transposed: Box<[_]> = matrices.iter()
+                .map(|m| [[m[0][0], m[1][0]], [m[0][1], m[1][1]]])
+                .collect();
+
+            // The gradient is Lipschitz with constant the product of the squared spectral norms
+            let step = matrices.iter()
+                .map(|m| {
+                    let (a, b, d) = (
+                        m[0][0]*m[0][0] + m[1][0]*m[1][0],
+                        m[0][0]*m[0][1] + m[1][0]*m[1][1],
+                        m[0][1]*m[0][1] + m[1][1]*m[1][1],
+                    );
+                    0.5 * (a + d + ((a - d)*(a - d) + 4.0*b*b).sqrt())
+                })
+                .product::<f64>()
+                .recip();
+
+            project_simplex(&mut result);
+
+            for _ in 0..1000 {
+                let mut residual = apply_tensor(result.clone(), &matrices);
+                for (state, f) in frequencies.iter() {
+                    *residual.entry(state.clone()).or_insert(0.0) -= f;
+                }
+
+                let mut next = result.clone();
+                for (state, g) in apply_tensor(residual, &transposed) {
+                    *next.entry(state).or_insert(0.0) -= step * g;
+                }
+                project_simplex(&mut next);
+
+                let delta: f64 = next.iter()
+                    .map(|(state, x)| (x - result.get(state).unwrap_or(&0.0)).abs())
+                    .sum();
+
+                result = next;
+
+                if delta < 1e-12 {
+                    break;
+                }
+            }
+        }
+
+        result
+    }
+
+    // Returns the frequency of every measured state.
+    fn frequencies(&self) -> HashMap<BitString, f64> {
+        self.measures.iter()
+            .map(|m| (m.state.clone(), m.frequency))
+            .collect()
+    }
+
+    // Checks that the states of the `probabilities` are as long as the measured ones.
+    fn check_distribution(&self, probabilities: &HashMap<BitString, f64>) {
+        for state in probabilities.keys() {
+            assert!(
+                state.len() == self.size as usize,
+                "The state {:?} is not {} bits long, like the measured states",
+                state,
+                self.size,
+            );
+        }
+    }
+
+    /// Returns the confidence interval `(low, high)` of the probability of measuring `state`, at the
+    /// given `confidence` level (such as `0.95`), computed with `method`.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if `confidence` is not in `(0, 1)`, or if `state` is not as long as
+    /// the measured states.
+    pub fn confidence_interval(&self, state: &BitString, confidence: f64, method: Interval) -> (f64, f64) {
+        assert!(
+            confidence > 0.0 && confidence < 1.0,
+            "The confidence level {} is not in (0, 1)",
+            confidence,
+        );
+        assert!(
+            state.len() == self.size as usize,
+            "The state {:?} is not {} bits long, like the measured states",
+            state,
+            self.size,
+        );
+
+        let count = self.measures.iter()
+            .find(|m| m.state == *state)
+            .map_or(0, |m| m.count);
+
+        method.bounds(count, self.samples, confidence)
+    }
+
+    /// Returns the confidence interval of the probability of every measured state, see
+    /// `Measurements::confidence_interval`, from most frequent to least frequent.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if `confidence` is not in `(0, 1)`.
+    pub fn confidence_intervals(&self, confidence: f64, method: Interval) -> Box<[(BitString, (f64, f64))]> {
+        assert!(
+            confidence > 0.0 && confidence < 1.0,
+            "The confidence level {} is not in (0, 1)",
+            confidence,
+        );
+
+        self.measures.iter()
+            .map(|m| (m.state.clone(), method.bounds(m.count, self.samples, confidence)))
+            .collect()
+    }
+
+    /// Returns the Shannon entropy, in bits, of the distribution of the measured states.
+    pub fn entropy(&self) -> f64 {
+        -self.measures.iter()
+            .map(|m| m.frequency * m.frequency.log2())
+            .sum::<f64>()
+    }
+
+    /// Returns the total variation distance `½ Σ |p(s) - q(s)|` between the distributions of the
+    /// measured states of `self` and `other`.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if the measured states are not of the same length.
+    pub fn total_variation_distance(&self, other: &Measurements) -> f64 {
+        let frequencies = other.frequencies();
+        self.check_distribution(&frequencies);
+        total_variation(&self.frequencies(), &frequencies)
+    }
+
+    /// Returns the total variation distance between the distribution of the measured states and
+    /// the exact `probabilities` of the states, the missing ones being null.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if the states are not as long as the measured ones.
+    pub fn total_variation_distance_to(&self, probabilities: &HashMap<BitString, f64>) -> f64 {
+        self.check_distribution(probabilities);
+        total_variation(&self.frequencies(), probabilities)
+    }
+
+    /// Returns the Hellinger distance `√(1 - Σ √(p(s) q(s)))` between the distributions of the
+    /// measured states of `self` and `other`.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if the measured states are not of the same length.
+    pub fn hellinger_distance(&self, other: &Measurements) -> f64 {
+        let frequencies = other.frequencies();
+        self.check_distribution(&frequencies);
+        hellinger(&self.frequencies(), &frequencies)
+    }
+
+    /// Returns the Hellinger distance between the distribution of the measured states and the exact
+    /// `probabilities` of the states, the missing ones being null.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if the states are not as long as the measured ones.
+    pub fn hellinger_distance_to(&self, probabilities: &HashMap<BitString, f64>) -> f64 {
+        self.check_distribution(probabilities);
+        hellinger(&self.frequencies(), probabilities)
+    }
+
+    /// Performs Pearson's chi-squared goodness of fit test of the measurements against the exact
+    /// `probabilities` of the states, the missing ones being null.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if the states are not as long as the measured ones, or if less than
+    /// two states have a nonzero probability.
+    pub fn chi_squared(&self, probabilities: &HashMap<BitString, f64>) -> ChiSquared {
+        self.check_distribution(probabilities);
+
+        let counts: HashMap<&BitString, usize> = self.measures.iter()
+            .map(|m| (&m.state, m.count))
+            .collect();
+
+        let expected: Vec<(&BitString, f64)> = probabilities.iter()
+            .filter(|(_, &p)| p > 0.0)
+            .map(|(state, &p)| (state, p * self.samples as f64))
+            .collect();
+
+        assert!(
+            expected.len() >= 2,
+            "The expected distribution needs at least two states of nonzero probability",
+        );
+
+        let unexpected = counts.keys().any(|state| probabilities.get(*state).is_none_or(|&p| p <= 0.0));
+
+        let statistic = if unexpected {
+            f64::INFINITY
+        } else {
+            expected.iter()
+                .map(|(state, e)| {
+                    let observed = *counts.get(state).unwrap_or(&0) as f64;
+                    (observed - e) * (observed - e) / e
+                })
+                .sum()
+        };
+
+        let degrees_of_freedom = expected.len() - 1;
+
+        ChiSquared {
+            statistic,
+            degrees_of_freedom,
+            p_value: chi_squared_survival(statistic, degrees_of_freedom),
+        }
+    }
+
+    /// Returns the number of times each state was measured, the states being written in the given
+    /// `format` and bit `order`, or in the bit order of the computer if `None`.
+    pub fn to_counts_map<O>(&self, format: StateFormat, order: O) -> HashMap<String, usize>
+    where
+        O: Into<Option<BitOrder>>,
+    {
+        let order = order.into().unwrap_or(self.bit_order);
+
+        self.measures.iter()
+            .map(|m| (format.format(&m.state, order), m.count))
+            .collect()
+    }
+
+    /// Exports the results as CSV, with a `state,count,frequency` row per measured state from most
+    /// frequent to least frequent, the states being written in the given `format` and bit `order`,
+    /// or in the bit order of the computer if `None`.
+    /// 
+    /// The rows are preceded by comment lines starting with `#` holding the duration in
+    /// milliseconds, the sample count, the measured qbits, their bases, the bit order and the
+    /// format of the states.
+    pub fn to_csv<O>(&self, format: StateFormat, order: O) -> String
+    where
+        O: Into<Option<BitOrder>>,
+    {
+        let order = order.into().unwrap_or(self.bit_order);
+
+        let join = |items: Vec<String>| items.join(" ");
+        let mut result = format!(
+            "# duration_ms: {}\n# samples: {}\n# qbits: {}\n# bases: {}\n# bit_order: {:?}\n# format: {:?}\nstate,count,frequency\n",
+            self.duration.as_secs_f64() * 1000.0,
+            self.samples,
+            join(self.qbits.iter().map(Address::to_string).collect()),
+            join(self.bases.iter().map(|pauli| format!("{:?}", pauli)).collect()),
+            order,
+            format,
+        );
+
+        for m in self.measures.iter() {
+            result.push_str(&format!("{},{},{}\n", format.format(&m.state, order), m.count, m.frequency));
+        }
+
+        result
+    }
+
+    /// Exports the results as a JSON object, holding the duration in milliseconds (`duration_ms`),
+    /// the sample count (`samples`), the measured qbits (`qbits`), their bases (`bases`), the bit
+    /// order (`bit_order`), the format of the states (`format`) and the number of times each state
+    /// was measured (`counts`), from most frequent to least frequent. The states are written in the
+    /// given `format` and bit `order`, or in the bit order of the computer if `None`.
+    pub fn to_json<O>(&self, format: StateFormat, order: O) -> String
+    where
+        O: Into<Option<BitOrder>>,
+    {
+        let order = order.into().unwrap_or(self.bit_order);
+
+        let qbits: Vec<String> = self.qbits.iter().map(Address::to_string).collect();
+        let bases: Vec<String> = self.bases.iter().map(|pauli| format!("\"{:?}\"", pauli)).collect();
+        let counts: Vec<String> = self.measures.iter()
+            .map(|m| format!("    \"{}\": {}", format.format(&m.state, order), m.count))
+            .collect();
+
+        format!(
+            "{{\n  \"duration_ms\": {},\n  \"samples\": {},\n  \"qbits\": [{}],\n  \"bases\": [{}],\n  \"bit_order\": \"{:?}\",\n  \"format\": \"{:?}\",\n  \"counts\": {{\n{}\n  }}\n}}\n",
+            self.duration.as_secs_f64() * 1000.0,
+            self.samples,
+            qbits.join(", "),
+            bases.join(", "),
+            order,
+            format,
+            counts.join(",\n"),
+        )
+    }
+
     /// Specifies the options for formatting the results:
     /// - `min_percentile` is the minimal percentile that results need to have been measured with
     /// in order to be displayed (default: `None`).
@@ -104,14 +830,329 @@ impl Measurements {
     }
 }
 
+impl Measurements {
+    /// Specifies the options of `Measurements::histogram`:
+    /// - `sort` is the order of the bars (default: `HistogramSort::Count`).
+    /// - if `by_register` is true, the values of every register are drawn as a separate histogram,
+    ///   the registers having a qbit that was not measured being skipped (default: `false`).
+    pub fn histogram_options(&mut self, sort: HistogramSort, by_register: bool) {
+        self.histogram_sort = sort;
+        self.histogram_by_register = by_register;
+    }
+
+    /// Draws the distribution of the measured states as a bar chart of at most `width` characters
+    /// wide bars, for display in a terminal. The displayed states follow the options of
+    /// `Measurements::format_options`, and the bars the ones of `Measurements::histogram_options`.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if `width` is 0.
+    pub fn histogram(&self, width: usize) -> String {
+        assert!(
+            width != 0,
+            "The width of the histogram cannot be 0",
+        );
+
+        let registers: Vec<&Register> = self.registers.iter()
+            .filter(|register| register.len() <= 64 && register.iter().all(|qbit| self.qbits.contains(&qbit)))
+            .collect();
+
+        if self.histogram_by_register && !registers.is_empty() {
+            registers.iter()
+                .map(|register| {
+                    let entries = self.register_counts(register, None).into_iter().collect();
+                    let bars = self.histogram_entries(entries, |value| value.to_string(), width);
+
+                    format!("{}:\n{}", register, bars)
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            // The states are sorted by their value as written, the first digit being the most
+            // significant bit
+            let written: Vec<usize> = match self.bit_order {
+                BitOrder::LittleEndian => (0..self.size as usize).collect(),
+                BitOrder::BigEndian => (0..self.size as usize).rev().collect(),
+            };
+            let entries = self.measures.iter()
+                .map(|m| ((m.state.select(&written), &m.state), m.count))
+                .collect();
+
+            self.histogram_entries(entries, |(_, state)| format!("|{}>", state.display(self.bit_order)), width)
+        }
+    }
+
+    // Renders the histogram of the `entries`, keys and counts, keeping the most frequent ones
+    // allowed by the format options. The bars are sorted by key with `HistogramSort::State`, and
+    // labelled with `label`.
+    fn histogram_entries<K, F>(&self, mut entries: Vec<(K, usize)>, label: F, width: usize) -> String
+    where
+        K: Ord,
+        F: Fn(&K) -> String,
+    {
+        let min = self.min_percentile.unwrap_or(0.0);
+        let max = self.max_display.unwrap_or(self.samples);
+
+        entries.sort_by(|(a, x), (b, y)| y.cmp(x).then_with(|| a.cmp(b)));
+
+        let shown = entries.iter()
+            .take(max)
+            .take_while(|&&(_, count)| count as f64 / self.samples as f64 >= min)
+            .count();
+        let hidden = entries.len() - shown;
+        entries.truncate(shown);
+
+        if self.histogram_sort == HistogramSort::State {
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        }
+
+        let entries: Vec<(String, usize)> = entries.iter()
+            .map(|(key, count)| (label(key), *count))
+            .collect();
+
+        histogram_bars(&entries, hidden, self.samples, width)
+    }
+}
+
+//#################################################################################################
+//
+//                                   Merging measurements
+//
+//#################################################################################################
+
+impl Measurements {
+    // Checks that the `other` measurements were obtained from the same measured qbits, bases and
+    // registers, so that their counts can be added.
+    fn check_compatible(&self, other: &Measurements) {
+        assert!(
+            self.qbits == other.qbits,
+            "Cannot merge measurements of the qbits {:?} with measurements of the qbits {:?}",
+            self.qbits,
+            other.qbits,
+        );
+        assert!(
+            self.bases == other.bases,
+            "Cannot merge measurements in the bases {:?} with measurements in the bases {:?}",
+            self.bases,
+            other.bases,
+        );
+        assert!(
+            self.registers == other.registers,
+            "Cannot merge measurements of the registers [{}] with measurements of the registers [{}]",
+            self.registers.iter().map(Register::to_string).collect::<Vec<_>>().join(", "),
+            other.registers.iter().map(Register::to_string).collect::<Vec<_>>().join(", "),
+        );
+    }
+
+    /// Adds the results of the `other` measurements, obtained by another run of the same program, to
+    /// these ones: the counts, samples and durations are summed, and the frequencies recomputed.
+    /// The display options and bit order of these measurements are kept. The shots of `other` are
+    /// kept after these ones if both kept their shots, and none are kept otherwise. The seed is kept
+    /// only if both runs used the same one.
+    ///
+    /// To merge a large number of runs, an `Accumulator` avoids sorting the states at every merge.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the `other` measurements were not obtained from the same measured
+    /// qbits, in the same bases and with the same registers.
+    pub fn merge(&mut self, other: &Measurements) {
+        self.check_compatible(other);
+
+        let mut counts: HashMap<BitString, usize> = self.counts()
+            .map(|(state, count)| (state.clone(), count))
+            .collect();
+        for (state, count) in other.counts() {
+            *counts.entry(state.clone()).or_insert(0) += count;
+        }
+
+        self.samples += other.samples;
+        self.duration += other.duration;
+        self.measures = sorted_measures(counts, self.samples);
+
+        match (&mut self.shots, &other.shots) {
+            (Some(shots), Some(other)) => shots.append(other),
+            _ => self.shots = None,
+        }
+
+        if self.seed != other.seed {
+            self.seed = None;
+        }
+    }
+}
+
+impl Add for Measurements {
+    type Output = Measurements;
+
+    fn add(mut self, other: Measurements) -> Measurements {
+        self.merge(&other);
+        self
+    }
+}
+
+impl AddAssign for Measurements {
+    fn add_assign(&mut self, other: Measurements) {
+        self.merge(&other);
+    }
+}
+
+impl Extend<Measurements> for Measurements {
+    fn extend<T: IntoIterator<Item = Measurements>>(&mut self, iter: T) {
+        let mut accumulator = Accumulator::new();
+        accumulator.add(self);
+        accumulator.extend(iter);
+
+        *self = accumulator.finish().unwrap();
+    }
+}
+
+impl<'a> Extend<&'a Measurements> for Measurements {
+    fn extend<T: IntoIterator<Item = &'a Measurements>>(&mut self, iter: T) {
+        let mut accumulator = Accumulator::new();
+        accumulator.add(self);
+        accumulator.extend(iter);
+
+        *self = accumulator.finish().unwrap();
+    }
+}
+
+/// Accumulates the results of many runs of the same program, for instance batches of shots run with
+/// different seeds or on different machines, by summing their counts. Unlike `Measurements::merge`,
+/// adding results does not sort the measured states again, which is done once by `finish`. The shots
+/// are kept in the order the results were added if all of them kept their shots, and the seed if all
+/// of them used the same one.
+///
+/// ```
+/// use trident::Accumulator;
+///
+/// let accumulator = Accumulator::new();
+/// assert_eq!(accumulator.samples(), 0);
+/// assert!(accumulator.finish().is_none());
+/// ```
+#[derive(Clone, Default)]
+pub struct Accumulator {
+    counts: HashMap<BitString, usize>,
+    samples: usize,
+    duration: Duration,
+    first: Option<Measurements>,
+}
+
+impl Accumulator {
+    /// Creates an empty accumulator.
+    pub fn new() -> Accumulator {
+        Accumulator::default()
+    }
+
+    /// Adds the counts, samples and duration of the `measurements`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the `measurements` were not obtained from the same measured qbits,
+    /// in the same bases and with the same registers as the previously added ones.
+    pub fn add(&mut self, measurements: &Measurements) {
+        match &mut self.first {
+            Some(first) => {
+                first.check_compatible(measurements);
+
+                match (&mut first.shots, &measurements.shots) {
+                    (Some(shots), Some(other)) => shots.append(other),
+                    _ => first.shots = None,
+                }
+
+                if first.seed != measurements.seed {
+                    first.seed = None;
+                }
+            },
+            None => self.first = Some(Measurements {
+                duration: Duration::default(),
+                size: measurements.size,
+                qbits: measurements.qbits.clone(),
+                bases: measurements.bases.clone(),
+                samples: 0,
+                measures: BTreeSet::new(),
+                registers: measurements.registers.clone(),
+                bit_order: measurements.bit_order,
+                min_percentile: measurements.min_percentile,
+                max_display: measurements.max_display,
+                histogram_sort: measurements.histogram_sort,
+                histogram_by_register: measurements.histogram_by_register,
+                shots: measurements.shots.clone(),
+                seed: measurements.seed,
+            }),
+        }
+
+        for (state, count) in measurements.counts() {
+            *self.counts.entry(state.clone()).or_insert(0) += count;
+        }
+
+        self.samples += measurements.samples;
+        self.duration += measurements.duration;
+    }
+
+    /// Returns the total number of samples added so far.
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Returns the total duration of the added computations.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Returns the merged measurements, with the display options and bit order of the first added
+    /// ones, or `None` if no measurements were added.
+    pub fn finish(self) -> Option<Measurements> {
+        let samples = self.samples;
+        let duration = self.duration;
+        let counts = self.counts;
+
+        self.first.map(|first| Measurements {
+            samples,
+            duration,
+            measures: sorted_measures(counts, samples),
+            ..first
+        })
+    }
+}
+
+impl Extend<Measurements> for Accumulator {
+    fn extend<T: IntoIterator<Item = Measurements>>(&mut self, iter: T) {
+        for measurements in iter {
+            self.add(&measurements);
+        }
+    }
+}
+
+impl<'a> Extend<&'a Measurements> for Accumulator {
+    fn extend<T: IntoIterator<Item = &'a Measurements>>(&mut self, iter: T) {
+        for measurements in iter {
+            self.add(measurements);
+        }
+    }
+}
+
 impl fmt::Display for Measurements {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, 
-            "[\n  [Measurements obtained in {} ms],\n  [Sample count of {}],\n  [Top results:\n", 
+            "[\n  [Measurements obtained in {} ms],\n  [Sample count of {}],\n", 
             self.duration.as_millis(),
             self.samples,
         ).unwrap();
 
+        if let Some(seed) = self.seed {
+            writeln!(f, "  [Seed {}],", seed).unwrap();
+        }
+
+        if self.bases.iter().any(|&pauli| pauli != Pauli::Z) {
+            let bases: Vec<String> = self.qbits.iter()
+                .zip(self.bases.iter())
+                .map(|(qbit, pauli)| format!("#{} in {:?}", qbit, pauli))
+                .collect();
+            writeln!(f, "  [Measurement bases {}],", bases.join(", ")).unwrap();
+        }
+
+        writeln!(f, "  [Top results:").unwrap();
+
         let len = self.measures.len();
 
         let min = match self.min_percentile {
@@ -131,14 +1172,295 @@ impl fmt::Display for Measurements {
             }
 
             write!(f,
-                "    |{:0size$b}> ~> {:5.2}%{}\n",
-                pair.state,
+                "    |{}> ~> {:5.2}%{}\n",
+                pair.state.display(self.bit_order),
                 pair.frequency * 100.0,
                 if i+1 == len {""} else {","},
-                size = self.size as usize,
             ).unwrap();
         }
 
         write!(f, "  ]\n]")
     }
-}
\ No newline at end of file
+}
+
+//#################################################################################################
+//
+//                                           Tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer::{Backend, Computer};
+
+    // The measurements of `size` qbits, the state of value `s` being measured `count` times for every
+    // `(s, count)` of `counts`.
+    fn measurements(size: usize, counts: &[(u64, usize)]) -> Measurements {
+        Measurements::new(
+            Duration::default(),
+            (0..size as Address).collect(),
+            vec![Pauli::Z; size].into_boxed_slice(),
+            counts.iter().map(|(_, count)| count).sum(),
+            counts.iter().map(|&(s, count)| (BitString::from_u64(s, size), count)).collect(),
+            Box::new([]),
+            BitOrder::LittleEndian,
+            None,
+            0,
+        )
+    }
+
+    fn distribution(size: usize, probabilities: &[(u64, f64)]) -> HashMap<BitString, f64> {
+        probabilities.iter()
+            .map(|&(s, p)| (BitString::from_u64(s, size), p))
+            .collect()
+    }
+
+    #[test]
+    fn chi_squared() {
+        let results = measurements(1, &[(0, 60), (1, 40)]);
+
+        let test = results.chi_squared(&distribution(1, &[(0, 0.5), (1, 0.5)]));
+        assert!((test.statistic - 4.0).abs() < 1e-12);
+        assert_eq!(test.degrees_of_freedom, 1);
+        assert!((test.p_value - 0.045500263896358396).abs() < 1e-12);
+
+        // A state of null expected probability was measured
+        let results = measurements(2, &[(0b00, 50), (0b11, 50)]);
+        let test = results.chi_squared(&distribution(2, &[(0b00, 0.5), (0b01, 0.5), (0b11, 0.0)]));
+        assert!(test.statistic.is_infinite());
+        assert_eq!(test.p_value, 0.0);
+    }
+
+    #[test]
+    fn confidence_intervals() {
+        let results = measurements(2, &[(0b00, 90), (0b11, 10)]);
+
+        let interval = results.confidence_interval(&BitString::from_u64(0b11, 2), 0.95, Interval::Wilson);
+        assert!((interval.0 - 0.0552291370606751).abs() < 1e-9);
+        assert!((interval.1 - 0.17436566150491345).abs() < 1e-9);
+
+        // A state never measured has a null lower bound
+        let interval = results.confidence_interval(&BitString::from_u64(0b01, 2), 0.95, Interval::ClopperPearson);
+        assert_eq!(interval.0, 0.0);
+        assert!((interval.1 - (1.0 - 0.025f64.powf(0.01))).abs() < 1e-12);
+    }
+
+    #[test]
+    fn most_frequent() {
+        let results = measurements(2, &[(0b00, 10), (0b01, 70), (0b10, 20)]);
+
+        assert_eq!(&*results.n_most(1), &[BitString::from_u64(0b01, 2)]);
+        assert_eq!(&*results.n_most(3), &[0b01, 0b10, 0b00].map(|s| BitString::from_u64(s, 2)));
+    }
+
+    // Returns the labels of the bars of the `histogram`.
+    fn labels(histogram: &str) -> Vec<&str> {
+        histogram.lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .collect()
+    }
+
+    #[test]
+    fn histogram_sorted_by_state() {
+        let mut results = measurements(4, &[(0b0010, 5), (0b1010, 3), (0b0001, 1)]);
+        results.registers = vec![Register::new("r", 0, 4)].into_boxed_slice();
+
+        results.histogram_options(HistogramSort::Count, false);
+        assert_eq!(labels(&results.histogram(10)), vec!["|0010>", "|1010>", "|0001>"]);
+
+        results.histogram_options(HistogramSort::State, false);
+        assert_eq!(labels(&results.histogram(10)), vec!["|0001>", "|0010>", "|1010>"]);
+
+        // Sorted by value, not by label
+        results.histogram_options(HistogramSort::State, true);
+        assert_eq!(labels(&results.histogram(10))[1..], ["1", "2", "10"]);
+
+        // Sorted as written
+        results.bit_order = BitOrder::BigEndian;
+        results.histogram_options(HistogramSort::State, false);
+        assert_eq!(labels(&results.histogram(10)), vec!["|0100>", "|0101>", "|1000>"]);
+    }
+
+    // The (state, count, frequency) of every measured state, in increasing order of state.
+    fn contents(results: &Measurements) -> Vec<(u64, usize, f64)> {
+        let mut contents: Vec<_> = results.measures.iter()
+            .map(|m| (m.state.to_u64().unwrap(), m.count, m.frequency))
+            .collect();
+        contents.sort_by_key(|&(state, _, _)| state);
+        contents
+    }
+
+    // Two runs of the same program of 2 qbits.
+    fn runs() -> (Measurements, Measurements) {
+        let mut first = measurements(2, &[(0b00, 30), (0b11, 10)]);
+        first.duration = Duration::from_millis(5);
+        let mut second = measurements(2, &[(0b11, 50), (0b01, 10)]);
+        second.duration = Duration::from_millis(7);
+        (first, second)
+    }
+
+    fn check_merged(results: &Measurements) {
+        assert_eq!(results.samples(), 100);
+        assert_eq!(results.duration(), Duration::from_millis(12));
+        assert_eq!(contents(results), vec![(0b00, 30, 0.3), (0b01, 10, 0.1), (0b11, 60, 0.6)]);
+    }
+
+    #[test]
+    fn merge() {
+        let (mut first, second) = runs();
+        first.merge(&second);
+        check_merged(&first);
+
+        let (first, second) = runs();
+        check_merged(&(first + second));
+
+        let (mut first, second) = runs();
+        first += second;
+        check_merged(&first);
+    }
+
+    #[test]
+    fn extend() {
+        let (mut first, second) = runs();
+        first.extend(vec![second]);
+        check_merged(&first);
+
+        let (mut first, second) = runs();
+        first.extend(&[second]);
+        check_merged(&first);
+
+        // Many runs at once
+        let (mut first, second) = runs();
+        let (third, fourth) = runs();
+        first.extend(vec![second, third, fourth]);
+        assert_eq!(first.samples(), 200);
+        assert_eq!(first.duration(), Duration::from_millis(24));
+        assert_eq!(contents(&first), vec![(0b00, 60, 0.3), (0b01, 20, 0.1), (0b11, 120, 0.6)]);
+    }
+
+    #[test]
+    fn accumulator() {
+        let (first, second) = runs();
+        let mut accumulator = Accumulator::new();
+        accumulator.add(&first);
+        assert_eq!(accumulator.samples(), 40);
+        assert_eq!(accumulator.duration(), Duration::from_millis(5));
+
+        accumulator.extend(vec![second]);
+        assert_eq!(accumulator.samples(), 100);
+        check_merged(&accumulator.finish().unwrap());
+
+        let (first, second) = runs();
+        let mut accumulator = Accumulator::new();
+        accumulator.extend(&[first, second]);
+        check_merged(&accumulator.finish().unwrap());
+    }
+
+    #[test]
+    fn merge_seeds() {
+        // Runs of the same seed keep it
+        let (mut first, second) = runs();
+        first += second;
+        assert_eq!(first.seed(), Some(0));
+
+        // Runs of different seeds can't be recreated from a single one
+        let (mut first, mut second) = runs();
+        second.seed = Some(1);
+        first += second;
+        assert_eq!(first.seed(), None);
+
+        let (first, mut second) = runs();
+        second.seed = Some(1);
+        let mut accumulator = Accumulator::new();
+        accumulator.extend(&[first, second]);
+        assert_eq!(accumulator.finish().unwrap().seed(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot merge measurements of the qbits")]
+    fn merge_other_qbits() {
+        let mut results = measurements(2, &[(0b00, 10)]);
+        results.merge(&measurements(3, &[(0b000, 10)]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot merge measurements in the bases")]
+    fn merge_other_bases() {
+        let mut results = measurements(2, &[(0b00, 10)]);
+        let mut other = measurements(2, &[(0b00, 10)]);
+        other.bases = vec![Pauli::X, Pauli::Z].into_boxed_slice();
+        results.merge(&other);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot merge measurements of the registers")]
+    fn merge_other_registers() {
+        let mut results = measurements(2, &[(0b00, 10)]);
+        let mut other = measurements(2, &[(0b00, 10)]);
+        other.registers = vec![Register::new("r", 0, 2)].into_boxed_slice();
+        results.merge(&other);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot merge measurements of the qbits")]
+    fn accumulate_other_qbits() {
+        let mut accumulator = Accumulator::new();
+        accumulator.extend(vec![measurements(2, &[(0b00, 10)]), measurements(1, &[(0, 10)])]);
+    }
+
+    // The measurements of the shots of `size` qbits of values `states`, keeping at most `limit`
+    // shots.
+    fn shots(size: usize, states: &[u64], limit: usize) -> Measurements {
+        let mut counts = HashMap::new();
+        let mut shots = Shots::new(size, limit, states.len());
+        for &s in states {
+            *counts.entry(s).or_insert(0) += 1;
+            shots.push(&BitString::from_u64(s, size));
+        }
+
+        let mut results = measurements(size, &counts.into_iter().collect::<Vec<_>>());
+        results.shots = Some(shots);
+        results
+    }
+
+    fn shot_values(results: &Measurements) -> Vec<u64> {
+        results.shots().unwrap().iter().map(|state| state.to_u64().unwrap()).collect()
+    }
+
+    #[test]
+    fn merge_shots() {
+        // The limit is the one of the program, not the samples of the first run
+        let mut results = shots(2, &[0b00, 0b11, 0b00], 10);
+        results.merge(&shots(2, &[0b11, 0b01], 10));
+        assert_eq!(shot_values(&results), vec![0b00, 0b11, 0b00, 0b11, 0b01]);
+        assert_eq!(results.samples(), 5);
+
+        let mut results = shots(2, &[0b00, 0b11, 0b00], 4);
+        results += shots(2, &[0b11, 0b01], 4);
+        assert_eq!(shot_values(&results), vec![0b00, 0b11, 0b00, 0b11]);
+
+        let mut accumulator = Accumulator::new();
+        accumulator.extend(vec![shots(2, &[0b01], 10), shots(2, &[0b10, 0b11], 10), shots(2, &[0b00], 10)]);
+        assert_eq!(shot_values(&accumulator.finish().unwrap()), vec![0b01, 0b10, 0b11, 0b00]);
+
+        // No shots are kept if one of the runs did not keep them
+        let mut results = shots(2, &[0b00], 10);
+        results.merge(&measurements(2, &[(0b11, 1)]));
+        assert!(results.shots().is_none());
+    }
+
+    #[test]
+    fn merge_runs_shots() {
+        // The shots are kept up to the limit of the program, not up to the samples of a single run
+        let mut computer = Computer::new(2).backend(Backend::Sparse).build();
+        let program = |computer: &Computer| computer.new_program("|11>").keep_shots(10).measure(3);
+
+        let mut results = computer.run(program(&computer), 0);
+        results.merge(&computer.run(program(&computer), 1));
+        assert_eq!(results.shots().unwrap().len(), 6);
+
+        results.merge(&computer.run(program(&computer), 2));
+        assert_eq!(shot_values(&results), vec![0b11; 9]);
+    }
+}
@@ -0,0 +1,712 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::bitstring::BitString;
+use crate::complex::{c128, c64};
+use crate::computer::Address;
+use crate::gates::Gate;
+use crate::linalg::singular_value_decomposition;
+use crate::program::Operation;
+use crate::random::Philox;
+use crate::state::StateVector;
+
+//#################################################################################################
+//
+//                                     Helper functions
+//
+//#################################################################################################
+
+// A gate acting on two neighbouring sites, indexed by 2*s1 + s2 where s1 is the state of the
+// leftmost site and s2 the one of the rightmost site.
+type TwoSiteGate = [[c128; 4]; 4];
+
+// Returns the two-site gate applying `gate` to one site, controlled by the other one.
+fn controlled(gate: &Gate, control_on_left: bool) -> TwoSiteGate {
+    let mut result = [[c128::ZERO; 4]; 4];
+    let u = [[gate.u00, gate.u01], [gate.u10, gate.u11]];
+
+    for a in 0..2 {
+        for b in 0..2 {
+            if control_on_left {
+                result[a][a] = c128::ONE;
+                result[2 + a][2 + b] = u[a][b].into();
+            } else {
+                result[2 * a][2 * a] = c128::ONE;
+                result[2 * a + 1][2 * b + 1] = u[a][b].into();
+            }
+        }
+    }
+
+    result
+}
+
+// Returns the two-site gate exchanging the states of the two sites.
+fn swap_gate() -> TwoSiteGate {
+    let mut result = [[c128::ZERO; 4]; 4];
+    result[0][0] = c128::ONE;
+    result[1][2] = c128::ONE;
+    result[2][1] = c128::ONE;
+    result[3][3] = c128::ONE;
+    result
+}
+
+// Returns the product of the column major matrices `a` (`rows`×`inner`) and `b` (`inner`×`cols`).
+fn multiply(a: &[c128], rows: usize, inner: usize, b: &[c128], cols: usize) -> Vec<c128> {
+    let mut result = vec![c128::ZERO; rows * cols];
+
+    for col in 0..cols {
+        for k in 0..inner {
+            let x = b[k + col * inner];
+            for row in 0..rows {
+                result[row + col * rows] = result[row + col * rows] + a[row + k * rows] * x;
+            }
+        }
+    }
+
+    result
+}
+
+// Returns the number of singular values that are not negligible.
+#[inline]
+fn rank(singular_values: &[f64]) -> usize {
+    let epsilon = singular_values[0] * 1e-14;
+    singular_values.iter()
+        .take_while(|&&s| s > epsilon)
+        .count()
+        .max(1)
+}
+
+//#################################################################################################
+//
+//                                          Tensor
+//
+//#################################################################################################
+
+// The tensor A[l][s][r] of a site, l and r being the indices of it's left and right bonds and s
+// the state of the qbit.
+#[derive(Clone)]
+struct Tensor {
+    left: usize,
+    right: usize,
+    // A[l][s][r] is stored at index l + left*(s + 2*right), so that the tensor is, without any
+    // copy, both the (2*left)×right and the left×(2*right) column major matrices
+    data: Vec<c128>,
+}
+
+impl Tensor {
+    #[inline]
+    fn get(&self, l: usize, s: usize, r: usize) -> c128 {
+        self.data[l + self.left * (s + 2 * r)]
+    }
+}
+
+//#################################################################################################
+//
+//                                   Matrix Product State
+//
+//#################################################################################################
+
+/// Represents the state of `size` qbits as a matrix product state, that is a chain of tensors, one
+/// per qbit, linked by bonds of limited dimension. The amplitude of the state `|s>` is the product
+/// of the matrices `A_i[s_i]` of every qbit.
+///
+/// Low-entanglement states are represented exactly with small bonds. When a two-qbit gate makes a
+/// bond grow beyond the maximal bond dimension, or creates singular values below the truncation
+/// threshold, the smallest singular values are discarded and their weight is added to the
+/// truncation error.
+#[derive(Clone)]
+pub struct MatrixProductState {
+    size: Address,
+    tensors: Box<[Tensor]>,
+    // The orthogonality center: the tensors on it's left are left-orthonormal, the ones on it's
+    // right are right-orthonormal
+    center: usize,
+    max_bond_dimension: usize,
+    truncation_threshold: f64,
+    truncation_error: f64,
+}
+
+impl MatrixProductState {
+    // Creates the matrix product state of `initial_state`, by splitting the qbits from the state one
+    // after the other with truncated singular value decompositions. Only the nonzero amplitudes are
+    // visited, so that basis states and sparse superpositions are cheap to build.
+    pub(crate) fn new(
+        size: Address,
+        initial_state: &StateVector,
+        max_bond_dimension: usize,
+        truncation_threshold: f64,
+    ) -> MatrixProductState {
+        let n = size as usize;
+
+        let mut mps = MatrixProductState {
+            size,
+            tensors: Box::new([]),
+            center: n - 1,
+            max_bond_dimension,
+            truncation_threshold,
+            truncation_error: 0.0,
+        };
+
+        // The columns of the matrix left to split, indexed by the states of the remaining qbits
+        let mut columns: Vec<(BitString, Vec<c128>)> = initial_state.iter()
+            .map(|(state, amplitude)| (state.clone(), vec![amplitude.into()]))
+            .collect();
+        let mut left = 1;
+        let mut tensors = Vec::with_capacity(n);
+
+        for site in 0..n - 1 {
+            // Rows are indexed by l + left*s, s being the state of the qbit of the site
+            let mut indices = HashMap::new();
+            let mut matrix: Vec<(BitString, Vec<c128>)> = Vec::new();
+
+            for (mut state, column) in columns {
+                let s = state.get(site) as usize;
+                state.set(site, false);
+
+                let j = *indices.entry(state.clone()).or_insert_with(|| {
+                    matrix.push((state, vec![c128::ZERO; 2 * left]));
+                    matrix.len() - 1
+                });
+                matrix[j].1[left * s..left * (s + 1)].copy_from_slice(&column);
+            }
+
+            let (rows, cols) = (2 * left, matrix.len());
+            let data: Vec<c128> = matrix.iter()
+                .flat_map(|(_, column)| column.iter().copied())
+                .collect();
+
+            let (u, s, v) = singular_value_decomposition(rows, cols, &data);
+            let (kept, norm) = mps.truncate(&s);
+
+            tensors.push(Tensor {
+                left,
+                right: kept,
+                data: u[..rows * kept].to_vec(),
+            });
+
+            // S V†
+            columns = matrix.into_iter()
+                .enumerate()
+                .map(|(j, (state, _))| {
+                    let column = (0..kept)
+                        .map(|k| v[j + cols * k].conjugate().scale(s[k] * norm))
+                        .collect();
+                    (state, column)
+                })
+                .collect();
+            left = kept;
+        }
+
+        let mut data = vec![c128::ZERO; 2 * left];
+        for (state, column) in columns {
+            let s = state.get(n - 1) as usize;
+            data[left * s..left * (s + 1)].copy_from_slice(&column);
+        }
+        tensors.push(Tensor {
+            left,
+            right: 1,
+            data,
+        });
+
+        mps.tensors = tensors.into();
+        mps
+    }
+
+    // Returns the maximal number of bytes used by the tensors of a `size` qbits state.
+    pub(crate) fn memory_usage(size: Address, max_bond_dimension: usize) -> usize {
+        // The bond on the left of the site i is at most of dimension 2^min(i, size-i)
+        let bond = |i: usize| if i == 0 || i == size as usize {
+            1
+        } else {
+            let exponent = std::cmp::min(i, size as usize - i) as u32;
+            2usize.checked_pow(exponent).map_or(max_bond_dimension, |x| x.min(max_bond_dimension))
+        };
+
+        (0..size as usize)
+            .map(|i| bond(i) * 2 * bond(i + 1) * std::mem::size_of::<c128>())
+            .sum()
+    }
+
+    // Applies the operations to the state, routing the control of distant two-qbit gates next to
+    // their target with swaps.
+    pub(crate) fn apply(&mut self, operations: &[Operation]) {
+        for operation in operations {
+            let target = operation.target as usize;
+
+            match operation.control {
+                None => self.apply_single(target, &operation.gate),
+                Some(control) => {
+                    let control = control as usize;
+
+                    if control < target {
+                        for site in control..target-1 {
+                            self.apply_two(site, &swap_gate());
+                        }
+                        self.apply_two(target - 1, &controlled(&operation.gate, true));
+                        for site in (control..target-1).rev() {
+                            self.apply_two(site, &swap_gate());
+                        }
+                    } else {
+                        for site in (target+1..control).rev() {
+                            self.apply_two(site, &swap_gate());
+                        }
+                        self.apply_two(target, &controlled(&operation.gate, false));
+                        for site in target+1..control {
+                            self.apply_two(site, &swap_gate());
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    // Applies the single qbit `gate` to the site.
+    fn apply_single(&mut self, site: usize, gate: &Gate) {
+        let (u00, u01, u10, u11): (c128, c128, c128, c128) = (gate.u00.into(), gate.u01.into(), gate.u10.into(), gate.u11.into());
+        let tensor = &mut self.tensors[site];
+        let left = tensor.left;
+
+        for r in 0..tensor.right {
+            for l in 0..left {
+                let (i0, i1) = (l + left * 2 * r, l + left * (1 + 2 * r));
+                let (x0, x1) = (tensor.data[i0], tensor.data[i1]);
+                tensor.data[i0] = u00 * x0 + u01 * x1;
+                tensor.data[i1] = u10 * x0 + u11 * x1;
+            }
+        }
+    }
+
+    // Applies the two-site `gate` to the sites (site, site+1) and splits them back with a truncated
+    // singular value decomposition.
+    fn apply_two(&mut self, site: usize, gate: &TwoSiteGate) {
+        self.move_center(site);
+
+        let (a, b) = (&self.tensors[site], &self.tensors[site + 1]);
+        let (left, middle, right) = (a.left, a.right, b.right);
+
+        // θ[l][s1][s2][r] = Σ A[l][s1][m] B[m][s2][r], as a (2*left)×(2*right) matrix
+        let theta = multiply(&a.data, 2 * left, middle, &b.data, 2 * right);
+        let (rows, cols) = (2 * left, 2 * right);
+
+        let mut gated = vec![c128::ZERO; rows * cols];
+        for r in 0..right {
+            for l in 0..left {
+                let index = |s1: usize, s2: usize| l + left * s1 + rows * (s2 + 2 * r);
+
+                for (i, row) in gate.iter().enumerate() {
+                    gated[index(i >> 1, i & 1)] = row.iter()
+                        .enumerate()
+                        .fold(c128::ZERO, |acc, (j, &g)| acc + g * theta[index(j >> 1, j & 1)]);
+                }
+            }
+        }
+
+        let (u, s, v) = singular_value_decomposition(rows, cols, &gated);
+
+        let (kept, norm) = self.truncate(&s);
+
+        self.tensors[site] = Tensor {
+            left,
+            right: kept,
+            data: u[..rows * kept].to_vec(),
+        };
+
+        let mut data = vec![c128::ZERO; kept * cols];
+        for col in 0..cols {
+            for k in 0..kept {
+                data[k + kept * col] = v[col + cols * k].conjugate().scale(s[k] * norm);
+            }
+        }
+
+        self.tensors[site + 1] = Tensor {
+            left: kept,
+            right,
+            data,
+        };
+        self.center = site + 1;
+    }
+
+    // Returns the number of singular values to keep, discarding the smallest ones while their
+    // weight stays below the threshold or while there are too many of them, and the factor
+    // renormalizing the kept ones. The discarded weight is added to the truncation error.
+    fn truncate(&mut self, s: &[f64]) -> (usize, f64) {
+        let total: f64 = s.iter().map(|x| x * x).sum();
+        let mut kept = rank(s);
+        let mut discarded = 0.0;
+        while kept > 1 && (
+            kept > self.max_bond_dimension ||
+            discarded + s[kept - 1] * s[kept - 1] <= self.truncation_threshold * total
+        ) {
+            discarded += s[kept - 1] * s[kept - 1];
+            kept -= 1;
+        }
+
+        self.truncation_error += discarded / total;
+        (kept, (total - discarded).sqrt().recip())
+    }
+
+    // Moves the orthogonality center to the `site`, without truncation.
+    fn move_center(&mut self, site: usize) {
+        while self.center < site {
+            let c = self.center;
+            let (left, right) = (self.tensors[c].left, self.tensors[c].right);
+
+            let (u, s, v) = singular_value_decomposition(2 * left, right, &self.tensors[c].data);
+            let kept = rank(&s);
+
+            // S V†, of dimension kept×right
+            let mut r = vec![c128::ZERO; kept * right];
+            for m in 0..right {
+                for k in 0..kept {
+                    r[k + kept * m] = v[m + right * k].conjugate().scale(s[k]);
+                }
+            }
+
+            self.tensors[c] = Tensor {
+                left,
+                right: kept,
+                data: u[..2 * left * kept].to_vec(),
+            };
+
+            let next = &self.tensors[c + 1];
+            let data = multiply(&r, kept, right, &next.data, 2 * next.right);
+            self.tensors[c + 1] = Tensor {
+                left: kept,
+                right: next.right,
+                data,
+            };
+
+            self.center += 1;
+        }
+
+        while self.center > site {
+            let c = self.center;
+            let (left, right) = (self.tensors[c].left, self.tensors[c].right);
+
+            let (u, s, v) = singular_value_decomposition(left, 2 * right, &self.tensors[c].data);
+            let kept = rank(&s);
+
+            // U S, of dimension left×kept
+            let mut l = vec![c128::ZERO; left * kept];
+            for k in 0..kept {
+                for m in 0..left {
+                    l[m + left * k] = u[m + left * k].scale(s[k]);
+                }
+            }
+
+            let mut data = vec![c128::ZERO; kept * 2 * right];
+            for col in 0..2 * right {
+                for k in 0..kept {
+                    data[k + kept * col] = v[col + 2 * right * k].conjugate();
+                }
+            }
+            self.tensors[c] = Tensor {
+                left: kept,
+                right,
+                data,
+            };
+
+            let previous = &self.tensors[c - 1];
+            let data = multiply(&previous.data, 2 * previous.left, left, &l, kept);
+            self.tensors[c - 1] = Tensor {
+                left: previous.left,
+                right: kept,
+                data,
+            };
+
+            self.center -= 1;
+        }
+    }
+
+    // Samples the state `samples` times and adds the results to `results`. The qbits are sampled
+    // one after the other from their conditional distributions, and the samples sharing the same
+    // first qbits are processed together.
+    pub(crate) fn sample(&mut self, samples: usize, prng: &mut Philox, results: &mut HashMap<BitString, usize>) {
+        // Every tensor on the right of the center is right-orthonormal, so the norm of the partial
+        // contraction of the first qbits is the marginal probability of their states
+        self.move_center(0);
+
+        // (site, state of the qbits before the site, partial contraction, sample count)
+        let mut stack = vec![(0, BitString::zeros(self.size as usize), vec![c128::ONE], samples)];
+
+        while let Some((site, state, vector, count)) = stack.pop() {
+            if site == self.size as usize {
+                *results.entry(state).or_insert(0) += count;
+                continue;
+            }
+
+            let tensor = &self.tensors[site];
+            let branches: Vec<(Vec<c128>, f64)> = (0..2)
+                .map(|s| {
+                    let w: Vec<c128> = (0..tensor.right)
+                        .map(|r| (0..tensor.left).fold(c128::ZERO, |acc, l| acc + vector[l] * tensor.get(l, s, r)))
+                        .collect();
+                    let p = w.iter().map(|x| x.norm_sqr()).sum();
+                    (w, p)
+                })
+                .collect();
+
+            let p0 = branches[0].1 / (branches[0].1 + branches[1].1);
+            let zeros = (0..count).filter(|_| prng.next_f64() < p0).count();
+
+            for (s, ((w, p), n)) in branches.into_iter().zip([zeros, count - zeros]).enumerate() {
+                if n != 0 {
+                    let norm = p.sqrt().recip();
+                    let w = w.into_iter().map(|x| x.scale(norm)).collect();
+                    let mut state = state.clone();
+                    state.set(site, s == 1);
+                    stack.push((site + 1, state, w, n));
+                }
+            }
+        }
+    }
+
+    /// Returns the number of qbits of the state.
+    pub fn size(&self) -> Address {
+        self.size
+    }
+
+    /// Returns the dimensions of the `size - 1` bonds between neighbouring qbits.
+    pub fn bond_dimensions(&self) -> Box<[usize]> {
+        self.tensors[..self.size as usize - 1]
+            .iter()
+            .map(|tensor| tensor.right)
+            .collect()
+    }
+
+    /// Returns the truncation error: the sum of the weights of every singular value discarded
+    /// while applying the gates. It is an upper bound estimate of `1 - F`, where `F` is the
+    /// fidelity of the matrix product state with the exact state.
+    pub fn truncation_error(&self) -> f64 {
+        self.truncation_error
+    }
+
+    /// Returns the amplitude `<state|ψ>`, the bit #i of `state` being the state of qbit #i.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if `state` is not `size` bits long.
+    pub fn amplitude(&self, state: &BitString) -> c64 {
+        self.contract(state).into()
+    }
+
+    /// Returns the probability `|<state|ψ>|²` of measuring `state`.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if `state` is not `size` bits long.
+    pub fn probability(&self, state: &BitString) -> f64 {
+        self.contract(state).norm_sqr()
+    }
+
+    // Returns the probability that the qbits #bits[i] are measured in the state of bit #i of `state`,
+    // by contracting the state with it's conjugate, the sites of the other qbits being traced out.
+    pub(crate) fn marginal_probability(&self, bits: &[usize], state: &BitString) -> f64 {
+        let mut constraints = vec![None; self.size as usize];
+        for (i, &bit) in bits.iter().enumerate() {
+            constraints[bit] = Some(state.get(i) as usize);
+        }
+
+        self.contract_conjugate(&constraints) / self.contract_conjugate(&vec![None; self.size as usize])
+    }
+
+    // Returns <ψ|P|ψ>, P projecting the site #i on the state constraints[i], if any.
+    fn contract_conjugate(&self, constraints: &[Option<usize>]) -> f64 {
+        // E[l][l'] = Σ conj(ψ_l) ψ_l', column major
+        let mut environment = vec![c128::ONE];
+
+        for (tensor, constraint) in self.tensors.iter().zip(constraints.iter()) {
+            let (left, right) = (tensor.left, tensor.right);
+            let mut next = vec![c128::ZERO; right * right];
+
+            for s in (0..2).filter(|&s| constraint.is_none_or(|c| c == s)) {
+                // T[l][r'] = Σ E[l][l'] A[l'][s][r']
+                let mut partial = vec![c128::ZERO; left * right];
+                for r2 in 0..right {
+                    for l2 in 0..left {
+                        let a = tensor.get(l2, s, r2);
+                        for l in 0..left {
+                            partial[l + r2 * left] = partial[l + r2 * left] + environment[l + l2 * left] * a;
+                        }
+                    }
+                }
+
+                // E'[r][r'] = Σ conj(A[l][s][r]) T[l][r']
+                for r2 in 0..right {
+                    for r in 0..right {
+                        next[r + r2 * right] = (0..left).fold(next[r + r2 * right], |acc, l| {
+                            acc + tensor.get(l, s, r).conjugate() * partial[l + r2 * left]
+                        });
+                    }
+                }
+            }
+
+            environment = next;
+        }
+
+        environment[0].0
+    }
+
+    // Returns the product of the matrices A_i[s_i].
+    fn contract(&self, state: &BitString) -> c128 {
+        assert!(
+            state.len() == self.size as usize,
+            "The state |{}> is not {} bits long",
+            state,
+            self.size,
+        );
+
+        self.tensors.iter()
+            .enumerate()
+            .fold(vec![c128::ONE], |vector, (qbit, tensor)| {
+                let s = state.get(qbit) as usize;
+                (0..tensor.right)
+                    .map(|r| (0..tensor.left).fold(c128::ZERO, |acc, l| acc + vector[l] * tensor.get(l, s, r)))
+                    .collect()
+            })[0]
+    }
+}
+
+impl fmt::Display for MatrixProductState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+            "[\n  [Matrix product state of {} qbits],\n  [Bond dimensions: {:?}],\n  [Truncation error: {:e}]\n]",
+            self.size,
+            self.bond_dimensions(),
+            self.truncation_error,
+        )
+    }
+}
+
+//#################################################################################################
+//
+//                                           Tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitstring::BitOrder;
+
+    fn hadamard() -> Gate {
+        let sqrt2inv = 2f32.sqrt().recip();
+        unsafe { Gate::new_unchecked(sqrt2inv, sqrt2inv, sqrt2inv, -sqrt2inv) }
+    }
+
+    fn cnot(control: Address, target: Address) -> Operation {
+        Operation {
+            gate_name: "X",
+            gate: unsafe { Gate::new_unchecked(0, 1, 1, 0) },
+            target,
+            control: Some(control),
+        }
+    }
+
+    // The matrix product state of `size` qbits, initially |00...0>, to which the `operations` are
+    // applied.
+    fn mps(size: Address, max_bond_dimension: usize, threshold: f64, operations: &[Operation]) -> MatrixProductState {
+        let zeros = StateVector::new(size, vec![(BitString::zeros(size as usize), c64::ONE)], false, BitOrder::LittleEndian);
+        let mut mps = MatrixProductState::new(size, &zeros, max_bond_dimension, threshold);
+        mps.apply(operations);
+        mps
+    }
+
+    // The operations preparing the GHZ state (|00...0> + |11...1>)/√2 of `size` qbits.
+    fn ghz(size: Address) -> Vec<Operation> {
+        let mut operations = vec![Operation {
+            gate_name: "H",
+            gate: hadamard(),
+            target: 0,
+            control: None,
+        }];
+        operations.extend((1..size).map(|qbit| cnot(qbit - 1, qbit)));
+        operations
+    }
+
+    fn ones(len: usize) -> BitString {
+        let mut state = BitString::zeros(len);
+        (0..len).for_each(|i| state.set(i, true));
+        state
+    }
+
+    #[test]
+    fn ghz_distribution() {
+        let mut state = mps(6, 16, 0.0, &ghz(6));
+
+        assert_eq!(&*state.bond_dimensions(), &[2; 5]);
+        assert!(state.truncation_error() < 1e-12);
+        assert!((state.probability(&BitString::zeros(6)) - 0.5).abs() < 1e-12);
+        assert!((state.probability(&ones(6)) - 0.5).abs() < 1e-12);
+        assert!(state.probability(&BitString::from_u64(0b000100, 6)) < 1e-12);
+        assert!((state.marginal_probability(&[3], &BitString::from_u64(1, 1)) - 0.5).abs() < 1e-12);
+        assert!(state.marginal_probability(&[1, 4], &BitString::from_u64(0b10, 2)) < 1e-12);
+
+        let mut results = HashMap::new();
+        state.sample(10000, &mut Philox::new(0), &mut results);
+
+        assert_eq!(results.len(), 2, "Sampled {:?} from a GHZ state", results.keys().collect::<Vec<_>>());
+        let zeros = results[&BitString::zeros(6)];
+        assert_eq!(zeros + results[&ones(6)], 10000);
+        assert!((zeros as f64 / 10000.0 - 0.5).abs() < 0.03, "{} zeros out of 10000", zeros);
+    }
+
+    #[test]
+    fn distant_gates() {
+        let mut operations = ghz(1);
+        operations.push(cnot(0, 5));
+        operations.push(cnot(5, 2));
+        let state = mps(6, 16, 0.0, &operations);
+
+        assert!((state.probability(&BitString::zeros(6)) - 0.5).abs() < 1e-12);
+        assert!((state.probability(&BitString::from_u64(0b100101, 6)) - 0.5).abs() < 1e-12);
+        assert!(state.truncation_error() < 1e-12);
+    }
+
+    #[test]
+    fn initial_superposition() {
+        let amplitude = c64::from(0.5f64.sqrt());
+        let initial = StateVector::new(
+            5,
+            vec![(BitString::zeros(5), amplitude), (ones(5), amplitude)],
+            false,
+            BitOrder::LittleEndian,
+        );
+        let state = MatrixProductState::new(5, &initial, 16, 0.0);
+
+        assert_eq!(&*state.bond_dimensions(), &[2; 4]);
+        assert!((state.probability(&ones(5)) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn truncation_by_bond_dimension() {
+        // The first CNOT creates two equal singular values, one of weight 1/2 is discarded
+        let state = mps(4, 1, 0.0, &ghz(4));
+
+        assert_eq!(&*state.bond_dimensions(), &[1; 3]);
+        assert!((state.truncation_error() - 0.5).abs() < 1e-12);
+
+        // The kept branch is renormalized
+        let p = state.probability(&BitString::zeros(4)) + state.probability(&ones(4));
+        assert!((p - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn truncation_by_threshold() {
+        let exact = mps(4, 16, 0.4, &ghz(4));
+        assert_eq!(&*exact.bond_dimensions(), &[2; 3]);
+        assert!(exact.truncation_error() < 1e-12);
+
+        let truncated = mps(4, 16, 0.6, &ghz(4));
+        assert_eq!(&*truncated.bond_dimensions(), &[1; 3]);
+        assert!((truncated.truncation_error() - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn memory_usage() {
+        // Bonds 1, 2, 4, 2, 1 and 1, 2, 2, 2, 1 for a maximal dimension of 2
+        let size = std::mem::size_of::<c128>();
+        assert_eq!(MatrixProductState::memory_usage(4, 16), (2 * 2 + 2 * 2 * 4 + 4 * 2 * 2 + 2 * 2) * size);
+        assert_eq!(MatrixProductState::memory_usage(4, 2), (2 * 2 + 2 * 2 * 2 + 2 * 2 * 2 + 2 * 2) * size);
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+
+use crate::complex::c64;
+use crate::computer::Address;
+use crate::gates::Gate;
+use crate::readout::ConfusionMatrix;
+
+//#################################################################################################
+//
+//                                     Helper functions
+//
+//#################################################################################################
+
+#[inline]
+fn close(x: c64, y: c64) -> bool {
+    (x - y).norm() < 1e-5
+}
+
+// Returns K†K.
+#[inline]
+fn dagger_product(k: &Gate) -> [c64; 4] {
+    [
+        k.u00.conjugate()*k.u00 + k.u10.conjugate()*k.u10,
+        k.u00.conjugate()*k.u01 + k.u10.conjugate()*k.u11,
+        k.u01.conjugate()*k.u00 + k.u11.conjugate()*k.u10,
+        k.u01.conjugate()*k.u01 + k.u11.conjugate()*k.u11,
+    ]
+}
+
+// Returns a gate which matrix is the one of `gate` multiplied by `scale`.
+#[inline]
+pub(crate) fn scale(gate: &Gate, scale: f32) -> Gate {
+    let scale = c64::from(scale);
+
+    unsafe {
+        Gate::new_unchecked(gate.u00 * scale, gate.u01 * scale, gate.u10 * scale, gate.u11 * scale)
+    }
+}
+
+#[inline]
+fn check_probability(p: f32) {
+    assert!(
+        (0.0..=1.0).contains(&p),
+        "The probability {} is not in [0, 1]",
+        p,
+    );
+}
+
+//#################################################################################################
+//
+//                                         Channel
+//
+//#################################################################################################
+
+/// Represents a noisy quantum channel acting on a single qbit, defined by it's Kraus operators
+/// `{K_i}`, such that the state `ρ` is mapped to `Σ K_i ρ K_i†`.
+#[derive(Clone)]
+pub struct Channel {
+    pub(crate) kraus: Box<[Gate]>,
+    // Whether every K_i†K_i is proportional to the identity, in which case the probabilities of
+    // the operators don't depend on the state
+    pub(crate) mixed_unitary: bool,
+}
+
+impl Channel {
+    /// Creates a new channel from it's Kraus operators.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `kraus` is empty or if the operators do not satisfy the
+    /// completeness relation `Σ K_i†K_i = 1`.
+    pub fn new(kraus: Vec<Gate>) -> Channel {
+        assert!(
+            !kraus.is_empty(),
+            "A channel needs at least one Kraus operator",
+        );
+
+        let sum = kraus.iter()
+            .map(dagger_product)
+            .fold([c64::ZERO; 4], |acc, x| [acc[0] + x[0], acc[1] + x[1], acc[2] + x[2], acc[3] + x[3]]);
+
+        assert!(
+            close(sum[0], c64::ONE) && close(sum[1], c64::ZERO) &&
+            close(sum[2], c64::ZERO) && close(sum[3], c64::ONE),
+            "The Kraus operators of the channel do not satisfy the completeness relation",
+        );
+
+        unsafe { Channel::new_unchecked(kraus) }
+    }
+
+    /// Unsafe version of the `Channel::new` function. Serves the same purpose and does the same thing,
+    /// but will not panic if the completeness relation is not satisfied.
+    ///
+    /// # Safety
+    ///
+    /// The Kraus operators must satisfy the completeness relation, otherwise the state will not be
+    /// correctly normalized during the simulation.
+    pub unsafe fn new_unchecked(kraus: Vec<Gate>) -> Channel {
+        let mixed_unitary = kraus.iter()
+            .map(dagger_product)
+            .all(|x| close(x[1], c64::ZERO) && close(x[2], c64::ZERO) && close(x[0], x[3]));
+
+        Channel {
+            kraus: kraus.into(),
+            mixed_unitary,
+        }
+    }
+
+    /// The bit flip channel, applying `X` with probability `p`.
+    pub fn bit_flip(p: f32) -> Channel {
+        check_probability(p);
+
+        unsafe {
+            Channel::new_unchecked(vec![
+                Gate::new_unchecked((1.0 - p).sqrt(), 0, 0, (1.0 - p).sqrt()),
+                Gate::new_unchecked(0, p.sqrt(), p.sqrt(), 0),
+            ])
+        }
+    }
+
+    /// The phase flip channel, applying `Z` with probability `p`.
+    pub fn phase_flip(p: f32) -> Channel {
+        check_probability(p);
+
+        unsafe {
+            Channel::new_unchecked(vec![
+                Gate::new_unchecked((1.0 - p).sqrt(), 0, 0, (1.0 - p).sqrt()),
+                Gate::new_unchecked(p.sqrt(), 0, 0, -p.sqrt()),
+            ])
+        }
+    }
+
+    /// The bit-phase flip channel, applying `Y` with probability `p`.
+    pub fn bit_phase_flip(p: f32) -> Channel {
+        check_probability(p);
+
+        unsafe {
+            Channel::new_unchecked(vec![
+                Gate::new_unchecked((1.0 - p).sqrt(), 0, 0, (1.0 - p).sqrt()),
+                Gate::new_unchecked(0, -c64::I * c64::from(p.sqrt()), c64::I * c64::from(p.sqrt()), 0),
+            ])
+        }
+    }
+
+    /// The depolarizing channel, replacing the state by the maximally mixed state with
+    /// probability `p`: `ρ -> (1-p)ρ + p/2 1`.
+    pub fn depolarizing(p: f32) -> Channel {
+        check_probability(p);
+
+        let (a, b) = ((1.0 - 0.75*p).sqrt(), (0.25*p).sqrt());
+
+        unsafe {
+            Channel::new_unchecked(vec![
+                Gate::new_unchecked(a, 0, 0, a),
+                Gate::new_unchecked(0, b, b, 0),
+                Gate::new_unchecked(0, -c64::I * c64::from(b), c64::I * c64::from(b), 0),
+                Gate::new_unchecked(b, 0, 0, -b),
+            ])
+        }
+    }
+
+    /// The amplitude damping channel, making the state `|1>` decay to `|0>` with
+    /// probability `gamma`.
+    pub fn amplitude_damping(gamma: f32) -> Channel {
+        check_probability(gamma);
+
+        unsafe {
+            Channel::new_unchecked(vec![
+                Gate::new_unchecked(1, 0, 0, (1.0 - gamma).sqrt()),
+                Gate::new_unchecked(0, gamma.sqrt(), 0, 0),
+            ])
+        }
+    }
+
+    /// The phase damping channel, losing the quantum phase information without loss of
+    /// energy, with probability `lambda`.
+    pub fn phase_damping(lambda: f32) -> Channel {
+        check_probability(lambda);
+
+        unsafe {
+            Channel::new_unchecked(vec![
+                Gate::new_unchecked(1, 0, 0, (1.0 - lambda).sqrt()),
+                Gate::new_unchecked(0, 0, 0, lambda.sqrt()),
+            ])
+        }
+    }
+
+    // Returns the probabilities of each of the Kraus operators given the reduced density matrix
+    // [[r00, r01], [r01*, r11]] of the qbit it acts on: p_i = Tr(K_i ρ K_i†).
+    pub(crate) fn probabilities(&self, r00: f32, r11: f32, r01: c64) -> Box<[f32]> {
+        self.kraus.iter()
+            .map(|k| {
+                let m = dagger_product(k);
+                // Tr(K ρ K†) = Tr(K†K ρ)
+                (m[0] * c64::from(r00) + m[3] * c64::from(r11) + m[1] * r01.conjugate() + m[2] * r01).re()
+            })
+            .collect()
+    }
+}
+
+//#################################################################################################
+//
+//                                        Noise Model
+//
+//#################################################################################################
+
+/// Describes the noise of a quantum computer: channels applied after each instruction and readout
+/// errors. Noisy programs are executed with Monte-Carlo trajectories on the state vector.
+#[derive(Clone)]
+pub struct NoiseModel {
+    pub(crate) gate_channels: HashMap<&'static str, Vec<Channel>>,
+    pub(crate) qbit_channels: HashMap<Address, Vec<Channel>>,
+    pub(crate) global_channels: Vec<Channel>,
+    pub(crate) readout_errors: HashMap<Address, ConfusionMatrix>,
+    pub(crate) trajectories: usize,
+}
+
+impl NoiseModel {
+    /// Creates a new, noiseless, noise model.
+    pub fn new() -> NoiseModel {
+        NoiseModel {
+            gate_channels: HashMap::new(),
+            qbit_channels: HashMap::new(),
+            global_channels: Vec::new(),
+            readout_errors: HashMap::new(),
+            trajectories: 100,
+        }
+    }
+
+    /// Adds a `channel`, applied to the qbits involved in every instruction.
+    pub fn channel(&mut self, channel: Channel) -> &mut NoiseModel {
+        self.global_channels.push(channel);
+        self
+    }
+
+    /// Adds a `channel`, applied to the qbits involved in every instruction using the gate named
+    /// `gate_name`.
+    pub fn gate_channel(&mut self, gate_name: &'static str, channel: Channel) -> &mut NoiseModel {
+        self.gate_channels.entry(gate_name)
+            .or_default()
+            .push(channel);
+        self
+    }
+
+    /// Adds a `channel`, applied to the qbit #`qbit` after every instruction involving it.
+    pub fn qbit_channel(&mut self, qbit: Address, channel: Channel) -> &mut NoiseModel {
+        self.qbit_channels.entry(qbit)
+            .or_default()
+            .push(channel);
+        self
+    }
+
+    /// Sets the readout error of the qbit #`qbit`: `p01` is the probability of reading a `1` when
+    /// the qbit is in state `|0>`, and `p10` the probability of reading a `0` when it is in state `|1>`.
+    pub fn readout_error(&mut self, qbit: Address, p01: f64, p10: f64) -> &mut NoiseModel {
+        self.readout_errors.insert(qbit, ConfusionMatrix::new(p01, p10));
+        self
+    }
+
+    /// Sets the number of Monte-Carlo trajectories the samples are split into (default: `100`).
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `trajectories` is 0.
+    pub fn trajectories(&mut self, trajectories: usize) -> &mut NoiseModel {
+        assert!(
+            trajectories != 0,
+            "Trajectories count cannot be 0",
+        );
+
+        self.trajectories = trajectories;
+        self
+    }
+
+    // Returns the channels to apply after an instruction using `gate_name` on the `qbit`.
+    pub(crate) fn channels<'a>(&'a self, gate_name: &str, qbit: Address) -> impl Iterator<Item = &'a Channel> {
+        self.global_channels.iter()
+            .chain(self.gate_channels.get(gate_name).into_iter().flatten())
+            .chain(self.qbit_channels.get(&qbit).into_iter().flatten())
+    }
+}
+
+impl Default for NoiseModel {
+    fn default() -> NoiseModel {
+        NoiseModel::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer::{Backend, Computer};
+
+    fn builtins(p: f32) -> Vec<Channel> {
+        vec![
+            Channel::bit_flip(p),
+            Channel::phase_flip(p),
+            Channel::bit_phase_flip(p),
+            Channel::depolarizing(p),
+            Channel::amplitude_damping(p),
+            Channel::phase_damping(p),
+        ]
+    }
+
+    #[test]
+    fn completeness() {
+        for &p in [0.0, 0.05, 0.5, 1.0].iter() {
+            for channel in builtins(p) {
+                let sum = channel.kraus.iter()
+                    .map(dagger_product)
+                    .fold([c64::ZERO; 4], |acc, x| [acc[0] + x[0], acc[1] + x[1], acc[2] + x[2], acc[3] + x[3]]);
+
+                assert!(
+                    close(sum[0], c64::ONE) && close(sum[1], c64::ZERO) &&
+                    close(sum[2], c64::ZERO) && close(sum[3], c64::ONE),
+                    "Σ K†K = {:?} for p = {}",
+                    sum,
+                    p,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn mixed_unitary_channels() {
+        let channels = builtins(0.3);
+        let mixed_unitary: Vec<bool> = channels.iter().map(|channel| channel.mixed_unitary).collect();
+
+        assert_eq!(mixed_unitary, vec![true, true, true, true, false, false]);
+    }
+
+    #[test]
+    fn amplitude_damping_probabilities() {
+        let channel = Channel::amplitude_damping(0.25);
+
+        // |0> never decays
+        let probabilities = channel.probabilities(1.0, 0.0, c64::ZERO);
+        assert!((probabilities[0] - 1.0).abs() < 1e-6 && probabilities[1].abs() < 1e-6);
+
+        let probabilities = channel.probabilities(0.0, 1.0, c64::ZERO);
+        assert!((probabilities[0] - 0.75).abs() < 1e-6 && (probabilities[1] - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    #[should_panic(expected = "do not satisfy the completeness relation")]
+    fn not_trace_preserving() {
+        let half = unsafe { Gate::new_unchecked(0.5, 0, 0, 0.5) };
+        Channel::new(vec![half, half]);
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one Kraus operator")]
+    fn no_kraus_operator() {
+        Channel::new(Vec::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "is not in [0, 1]")]
+    fn invalid_probability() {
+        Channel::depolarizing(1.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "are not in [0, 1]")]
+    fn invalid_readout_error() {
+        NoiseModel::new().readout_error(0, 0.1, -0.1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Trajectories count cannot be 0")]
+    fn no_trajectory() {
+        NoiseModel::new().trajectories(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "only supported by the StateVector backend")]
+    fn unsupported_backend() {
+        Computer::new(2)
+            .backend(Backend::DensityMatrix)
+            .noise_model(NoiseModel::new().channel(Channel::bit_flip(0.1)))
+            .build();
+    }
+
+    #[test]
+    #[should_panic(expected = "refers to the gate \"T\"")]
+    fn unknown_gate() {
+        Computer::new(2)
+            .noise_model(NoiseModel::new().gate_channel("T", Channel::bit_flip(0.1)))
+            .build();
+    }
+
+    #[test]
+    #[should_panic(expected = "refers to qbit #2")]
+    fn unknown_qbit() {
+        Computer::new(2)
+            .noise_model(NoiseModel::new().qbit_channel(2, Channel::bit_flip(0.1)))
+            .build();
+    }
+
+    #[test]
+    #[should_panic(expected = "refers to qbit #3")]
+    fn unknown_readout_qbit() {
+        Computer::new(2)
+            .noise_model(NoiseModel::new().readout_error(3, 0.1, 0.1))
+            .build();
+    }
+}
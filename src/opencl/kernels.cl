@@ -21,6 +21,27 @@ static inline size_t nth_cleared(
     return (n & mask) | ((n & ~mask) << 1);
 }
 
+// Spreads the bits of value over the positions of the set bits of mask: the #i bit of value becomes
+// the #i lowest set bit of mask
+static inline size_t deposit(
+    size_t value,
+    ulong mask
+) {
+    size_t result = 0;
+
+    for (; mask; value >>= 1) {
+        const ulong lowest = mask & (~mask + 1);
+
+        if (value & 1) {
+            result |= lowest;
+        }
+
+        mask ^= lowest;
+    }
+
+    return result;
+}
+
 // Returns the proper index corresponding to the #id element at the #pass level of the
 // distribution vector
 static inline size_t index(
@@ -32,59 +53,44 @@ static inline size_t index(
 
 //#################################################################################################
 //
-//                                          MWC64X prng
+//                                       Philox4x32-10 prng
 // 
 //#################################################################################################
 
-// Returns (a+b) % m if a < m && b < m
-static inline ulong modular_add64(ulong a, ulong b, ulong m) {
-    ulong res = a + b;
-
-    if ((res >= m) || (res < a)) res -= m;
-
-    return res;
-}
-
-// Returns (a*b) % m if a < m && b < m
-static inline ulong modular_mul64(ulong a, ulong b, ulong m) {
-    ulong res = 0;
-
-    while (a) {
-        if (a & 1) res = modular_add64(res, b, m);
-        b = modular_add64(b, b, m);
-        a >>= 1;
-    }
-
-    return res;
-}
-
-// Returns (a**e) % m if a < m && e < m
-static inline ulong modular_pow64(ulong a, ulong e, ulong m) {
-    ulong sqr = a, acc = 1;
+// Index of the sampling stream, shared with the host generator
+#define SAMPLING_STREAM 0
 
-    while (e) {
-        if (e & 1) acc = modular_add64(acc, sqr, m);
-        sqr = modular_add64(sqr, sqr, m);
-        e >>= 1;
+// Returns the block of random numbers of the counter with the key, with the 10 rounds of the
+// Philox4x32 function
+static inline uint4 philox(
+    uint4 counter,
+    uint2 key
+) {
+    const uint M0 = 0xD2511F53;
+    const uint M1 = 0xCD9E8D57;
+    const uint2 W = (uint2) (0x9E3779B9, 0xBB67AE85);
+
+    for (uchar i = 0; i < 10; i++) {
+        const uint hi0 = mul_hi(M0, counter.x);
+        const uint lo0 = M0 * counter.x;
+        const uint hi1 = mul_hi(M1, counter.z);
+        const uint lo1 = M1 * counter.z;
+
+        counter = (uint4) (hi1 ^ counter.y ^ key.x, lo1, hi0 ^ counter.w ^ key.y, lo0);
+        key += W;
     }
 
-    return acc;
+    return counter;
 }
 
-// Returns a random float from [0,1] based off the state and the global_id (distance)
+// Returns a random float from [0,1) drawn from the substream #shot of the sampling stream
 static inline float random(
-    const uint2 state,
-    const uint distance
+    const uint2 key,
+    const ulong shot
 ) {
-    const ulong A = 4294883355;
-    const ulong M = 18446383549859758079;
+    const uint4 block = philox((uint4) (0, (uint) shot, (uint) (shot >> 32), SAMPLING_STREAM), key);
 
-    const ulong m = modular_pow64(A, distance, M);
-    ulong x = (ulong) state.x * A + (ulong) state.y;
-    x = modular_mul64(x, m, M);
-    x = (x / A) ^ (x % A);
-
-    return (float) ((double) x * 2.3283064370807974e-10);
+    return (float) (block.x >> 8) * 5.9604644775390625e-8f;
 }
 
 //#################################################################################################
@@ -157,6 +163,81 @@ kernel void calculate_probabilities(
     buffer[global_id].x = value.x + value.y;
 }
 
+// Calculate the probabilites of a density matrix of #size qbits by extracting it's diagonal and
+// storing it in the real parts of the first 2^size complex numbers of the buffer
+kernel void calculate_diagonal_probabilities(
+    global float2 *buffer,
+    const uchar size
+) {
+    const size_t global_id = get_global_id(0);
+    const size_t dim = (size_t) 1 << size;
+
+    buffer[global_id].x = buffer[global_id * (dim + 1)].x;
+}
+
+// Calculate, for each pair of amplitudes (a, b) differing only by the #target qbit, the contributions
+// (|a|², |b|², a b*) to the reduced density matrix of that qbit
+kernel void qbit_density(
+    global const float2 *buffer,
+    global float4 *densities,
+    const uchar target
+) {
+    const size_t global_id = get_global_id(0);
+
+    const size_t zero_state = nth_cleared(global_id, target);
+    const size_t one_state  = zero_state | ((size_t) 1 << target);
+
+    const float2 zero_amp = buffer[zero_state];
+    const float2 one_amp  = buffer[one_state];
+
+    const float2 product = complex_mul(zero_amp, (float2) (one_amp.x, -one_amp.y));
+
+    densities[global_id] = (float4) (dot(zero_amp, zero_amp), dot(one_amp, one_amp), product.x, product.y);
+}
+
+// Add the second half of the buffer, starting at #half, to it's first half
+kernel void reduce_sum(
+    global float4 *buffer,
+    const ulong half
+) {
+    const size_t global_id = get_global_id(0);
+
+    buffer[global_id] += buffer[global_id + half];
+}
+
+// Calculate the marginal probabilities of the qbits set in #measured, out of the #size qbits, by
+// summing the probabilities stored in the real parts of the buffer over the other qbits. The
+// probability of the #id measured state, it's #i bit being the #i lowest measured qbit, is stored
+// in the imaginary part of the #id complex number
+kernel void marginal_probabilities(
+    global float2 *buffer,
+    const ulong measured,
+    const uchar size
+) {
+    const size_t global_id = get_global_id(0);
+
+    const ulong unmeasured = ~measured & (ulong) (((size_t) 1 << size) - 1);
+    const size_t measured_state = deposit(global_id, measured);
+    const size_t count = (size_t) 1 << popcount(unmeasured);
+
+    float sum = 0.0;
+
+    for (size_t t = 0; t < count; t++) {
+        sum += buffer[measured_state | deposit(t, unmeasured)].x;
+    }
+
+    buffer[global_id].y = sum;
+}
+
+// Move the marginal probabilities from the imaginary parts of the buffer to it's real parts
+kernel void move_marginal_probabilities(
+    global float2 *buffer
+) {
+    const size_t global_id = get_global_id(0);
+
+    buffer[global_id].x = buffer[global_id].y;
+}
+
 // Reduce the distribution vector
 kernel void reduce_distribution(
     global float *buffer,
@@ -171,15 +252,17 @@ kernel void reduce_distribution(
     buffer[id] = buffer[id0] + buffer[id1];
 }
 
-// Perform measurements by traversing the distribution vector
+// Perform measurements by traversing the distribution vector, the #i measurement being the shot
+// #first+i
 kernel void do_measurements(
     global const float *buffer,
     global ulong *mesures,
     uchar size,
-    const uint2 state
+    const uint2 key,
+    const ulong first
 ) {
     const size_t global_id = get_global_id(0);
-    const float rand = random(state, global_id);
+    const float rand = random(key, first + global_id);
 
     size_t id = 0;
     float sum = 0.0;
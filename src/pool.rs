@@ -0,0 +1,363 @@
+use std::collections::VecDeque;
+use std::mem::replace;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+use crate::computer::Computer;
+use crate::measure::Measurements;
+use crate::program::Program;
+use crate::random::SeedSource;
+
+//#################################################################################################
+//
+//                                           Jobs
+//
+//#################################################################################################
+
+/// The status of a job submitted to a `ComputerPool`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum JobStatus {
+    /// The job waits for a computer of the pool.
+    Queued,
+    /// The program of the job is running on one of the computers.
+    Running,
+    /// The measurements of the job are ready.
+    Done,
+    /// The job was cancelled before running, by `JobHandle::cancel` or because the pool was
+    /// dropped.
+    Cancelled,
+}
+
+// The state of a job, shared by the pool and it's handle.
+enum JobState {
+    Queued,
+    Running,
+    Done(thread::Result<Measurements>),
+    Cancelled,
+}
+
+// The outcome of a job, waited for by it's handle.
+struct Slot {
+    state: Mutex<JobState>,
+    changed: Condvar,
+}
+
+impl Slot {
+    fn lock(&self) -> MutexGuard<'_, JobState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Sets the state of the job, and wakes the handle waiting for it.
+    fn set(&self, state: JobState) {
+        *self.lock() = state;
+        self.changed.notify_all();
+    }
+}
+
+// A program waiting for a computer.
+struct Job {
+    id: u64,
+    program: Program,
+    seed: u64,
+    slot: Arc<Slot>,
+}
+
+//#################################################################################################
+//
+//                                           Queue
+//
+//#################################################################################################
+
+struct QueueState {
+    jobs: VecDeque<Job>,
+    next_id: u64,
+    // Set when the pool is dropped, the workers stop once their current job is done
+    closed: bool,
+}
+
+// The queue of the jobs, shared by the pool, the workers and the handles.
+struct Queue {
+    state: Mutex<QueueState>,
+    available: Condvar,
+}
+
+impl Queue {
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // Returns the next job, waiting for one if the queue is empty, or `None` if the pool is dropped.
+    fn next(&self) -> Option<Job> {
+        let mut state = self.lock();
+        loop {
+            if state.closed {
+                return None;
+            }
+            if let Some(job) = state.jobs.pop_front() {
+                // Marked as running under the lock of the queue, so that it cannot be cancelled
+                // in between
+                job.slot.set(JobState::Running);
+                return Some(job);
+            }
+            state = self.available.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+    }
+
+    // Removes the job #id from the queue, returning true if it was still queued.
+    fn cancel(&self, id: u64) -> bool {
+        let mut state = self.lock();
+        match state.jobs.iter().position(|job| job.id == id) {
+            Some(position) => {
+                if let Some(job) = state.jobs.remove(position) {
+                    job.slot.set(JobState::Cancelled);
+                }
+                true
+            },
+            None => false,
+        }
+    }
+
+    // Cancels every queued job, returning how many there were.
+    fn cancel_all(&self) -> usize {
+        let jobs: Vec<Job> = self.lock().jobs.drain(..).collect();
+        for job in jobs.iter() {
+            job.slot.set(JobState::Cancelled);
+        }
+        jobs.len()
+    }
+}
+
+//#################################################################################################
+//
+//                                        Job handle
+//
+//#################################################################################################
+
+/// The handle of a job submitted to a `ComputerPool`, through which it's measurements are received.
+/// Dropping it doesn't cancel the job.
+pub struct JobHandle {
+    id: u64,
+    slot: Arc<Slot>,
+    queue: Arc<Queue>,
+}
+
+impl JobHandle {
+    /// Returns the identifier of the job, unique among the jobs of the pool.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the current status of the job.
+    pub fn status(&self) -> JobStatus {
+        match *self.slot.lock() {
+            JobState::Queued => JobStatus::Queued,
+            JobState::Running => JobStatus::Running,
+            JobState::Done(_) => JobStatus::Done,
+            JobState::Cancelled => JobStatus::Cancelled,
+        }
+    }
+
+    /// Removes the job from the queue if it is still waiting for a computer, and returns true if it
+    /// was. A job already running or done cannot be cancelled.
+    pub fn cancel(&self) -> bool {
+        self.queue.cancel(self.id)
+    }
+
+    /// Blocks the current thread until the job is over, and returns it's measurements, or `None` if
+    /// the job was cancelled.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if running the program panicked, in the same cases as
+    /// `Computer::run`.
+    pub fn wait(self) -> Option<Measurements> {
+        let mut state = self.slot.lock();
+        while let JobState::Queued | JobState::Running = *state {
+            state = self.slot.changed.wait(state).unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+
+        // The handle is consumed, the state is not read any more
+        match replace(&mut *state, JobState::Cancelled) {
+            JobState::Done(Ok(measurements)) => Some(measurements),
+            JobState::Done(Err(panic)) => resume_unwind(panic),
+            _ => None,
+        }
+    }
+}
+
+//#################################################################################################
+//
+//                                      Computer pool
+//
+//#################################################################################################
+
+/// A pool of computers sharing a queue of jobs: programs can be submitted from many threads, and are
+/// run in order by the first available computer, each one on it's own worker thread. The computers
+/// can for instance be on different devices, but must be able to run the same programs.
+///
+/// Dropping the pool cancels the queued jobs, and waits for the running ones.
+pub struct ComputerPool {
+    // A copy of the first computer without it's simulator, to build programs
+    template: Mutex<Computer>,
+    queue: Arc<Queue>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl ComputerPool {
+    /// Creates a pool running jobs on the `computers`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if there are no computers, if they are not of the same size, with
+    /// the same gates and bit order, or if a worker thread cannot be spawned.
+    pub fn new<I>(computers: I) -> ComputerPool
+    where
+        I: IntoIterator<Item = Computer>,
+    {
+        let computers: Vec<Computer> = computers.into_iter().collect();
+
+        assert!(
+            !computers.is_empty(),
+            "A computer pool needs at least one computer"
+        );
+
+        let template = computers[0].detached();
+        for computer in computers.iter() {
+            assert!(
+                computer.size == template.size && computer.bit_order == template.bit_order,
+                "The computers of a pool must be of the same size and bit order",
+            );
+
+            let mut gates: Vec<&str> = computer.gates.keys().copied().collect();
+            let mut expected: Vec<&str> = template.gates.keys().copied().collect();
+            gates.sort_unstable();
+            expected.sort_unstable();
+            assert!(
+                gates == expected,
+                "The computers of a pool must have the same gates, but one has {:?} and the first one {:?}",
+                gates,
+                expected,
+            );
+        }
+
+        let queue = Arc::new(Queue {
+            state: Mutex::new(QueueState {
+                jobs: VecDeque::new(),
+                next_id: 0,
+                closed: false,
+            }),
+            available: Condvar::new(),
+        });
+
+        let workers = computers.into_iter()
+            .enumerate()
+            .map(|(i, mut computer)| {
+                let queue = Arc::clone(&queue);
+
+                thread::Builder::new()
+                    .name(format!("trident-pool-{}", i))
+                    .spawn(move || {
+                        while let Some(job) = queue.next() {
+                            let program = job.program;
+                            let seed = job.seed;
+
+                            let measurements = catch_unwind(AssertUnwindSafe(|| computer.run(program, seed)));
+                            job.slot.set(JobState::Done(measurements));
+                        }
+                    })
+                    .expect("Cannot spawn the worker thread of the pool")
+            })
+            .collect();
+
+        ComputerPool {
+            template: Mutex::new(template),
+            queue,
+            workers,
+        }
+    }
+
+    /// Returns the number of computers of the pool.
+    pub fn computers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Builds a program for the computers of the pool, with `build`, which is given a computer on
+    /// which to call `Computer::new_program`. This computer cannot run programs.
+    ///
+    /// ```no_run
+    /// use trident::{Computer, ComputerPool, InstructionChain};
+    ///
+    /// let pool = ComputerPool::new(vec![Computer::new(2).add_default_gates().build()]);
+    /// let program = pool.build_program(|computer| {
+    ///     computer.new_program("|00>")
+    ///         .apply("H", 0, None)
+    ///         .apply("X", 1, 0)
+    ///         .measure(100)
+    /// });
+    /// let results = pool.submit(program, None).wait();
+    /// ```
+    pub fn build_program<F>(&self, build: F) -> Program
+    where
+        F: FnOnce(&Computer) -> Program,
+    {
+        let template = self.template.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        build(&template)
+    }
+
+    /// Adds the `program` at the end of the queue, and returns the handle of the job. The seed is
+    /// drawn from the `seed` source when the job is submitted.
+    pub fn submit<S>(&self, program: Program, seed: S) -> JobHandle
+    where
+        S: Into<SeedSource>,
+    {
+        let seed = seed.into().seed();
+        let slot = Arc::new(Slot {
+            state: Mutex::new(JobState::Queued),
+            changed: Condvar::new(),
+        });
+
+        let id = {
+            let mut state = self.queue.lock();
+            let id = state.next_id;
+            state.next_id += 1;
+
+            state.jobs.push_back(Job {
+                id,
+                program,
+                seed,
+                slot: Arc::clone(&slot),
+            });
+            id
+        };
+        self.queue.available.notify_one();
+
+        JobHandle {
+            id,
+            slot,
+            queue: Arc::clone(&self.queue),
+        }
+    }
+
+    /// Returns the number of jobs waiting for a computer.
+    pub fn queued(&self) -> usize {
+        self.queue.lock().jobs.len()
+    }
+
+    /// Cancels every job waiting for a computer, and returns how many were cancelled.
+    pub fn cancel_all(&self) -> usize {
+        self.queue.cancel_all()
+    }
+}
+
+impl Drop for ComputerPool {
+    fn drop(&mut self) {
+        self.queue.cancel_all();
+        self.queue.lock().closed = true;
+        self.queue.available.notify_all();
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
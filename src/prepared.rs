@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::bitstring::{BitOrder, BitString};
+use crate::computer::Address;
+use crate::gates::Pauli;
+use crate::gpu::{GpuDistribution, GpuState};
+use crate::measure::{Measurements, Shots};
+use crate::mps::MatrixProductState;
+use crate::program::Program;
+use crate::random::{Philox, SeedSource, Stream};
+use crate::readout::ConfusionMatrix;
+use crate::register::Register;
+use crate::sparse::SparseState;
+use crate::stabilizer::AffineDistribution;
+
+//#################################################################################################
+//
+//                                          Readout
+//
+//#################################################################################################
+
+// Applies the readout errors to the sampled states, and keeps the shots if asked to.
+pub(crate) struct Readout<'a> {
+    // The readout errors of the measured qbits, at their position in the measured states
+    errors: &'a [(Address, ConfusionMatrix)],
+    prng: &'a Philox,
+    // Index of the next shot read out
+    shot: u64,
+    shots: Option<Shots>,
+}
+
+impl<'a> Readout<'a> {
+    // Keeps at most `kept_shots` of the `samples` shots of `width` bits, if any. The errors of the
+    // shot #i are drawn from the substream #i of the readout stream of `prng`.
+    pub(crate) fn new(
+        errors: &'a [(Address, ConfusionMatrix)],
+        prng: &'a Philox,
+        kept_shots: Option<usize>,
+        width: usize,
+        samples: usize,
+    ) -> Readout<'a> {
+        let shots = kept_shots.map(|max| Shots::new(width, max, std::cmp::min(max, samples)));
+
+        Readout {
+            errors,
+            prng,
+            shot: 0,
+            shots,
+        }
+    }
+
+    // Returns the state read out for the sampled `state` of the next shot, after the readout errors.
+    pub(crate) fn apply(&mut self, mut state: BitString) -> BitString {
+        if !self.errors.is_empty() {
+            let mut prng = self.prng.substream(Stream::Readout, self.shot);
+            for (qbit, confusion) in self.errors.iter() {
+                confusion.apply(&mut state, *qbit, &mut prng);
+            }
+        }
+        self.shot += 1;
+
+        if let Some(shots) = &mut self.shots {
+            shots.push(&state);
+        }
+        state
+    }
+
+    // Reads out the `samples`, given as counts, and adds them to the `results`.
+    pub(crate) fn apply_counts(
+        &mut self,
+        samples: HashMap<BitString, usize>,
+        results: &mut HashMap<BitString, usize>,
+    ) {
+        // Sorted so that the random numbers are always drawn in the same order
+        let mut samples: Vec<_> = samples.into_iter().collect();
+        samples.sort_unstable();
+
+        if self.shots.is_none() {
+            for (state, count) in samples {
+                for _ in 0..count {
+                    *results.entry(self.apply(state.clone())).or_insert(0) += 1;
+                }
+            }
+            return;
+        }
+
+        // The shots are read out in a uniformly random order, which is the order of independent
+        // shots
+        let mut shots: Vec<usize> = samples.iter()
+            .enumerate()
+            .flat_map(|(i, &(_, count))| std::iter::repeat_n(i, count))
+            .collect();
+        let mut prng = self.prng.substream(Stream::Order, 0);
+        for i in (1..shots.len()).rev() {
+            let j = (prng.next_f64() * (i + 1) as f64) as usize;
+            shots.swap(i, j);
+        }
+
+        for i in shots {
+            *results.entry(self.apply(samples[i].0.clone())).or_insert(0) += 1;
+        }
+    }
+
+    // Returns the kept shots, if any.
+    pub(crate) fn into_shots(self) -> Option<Shots> {
+        self.shots
+    }
+}
+
+//#################################################################################################
+//
+//                                       Prepared state
+//
+//#################################################################################################
+
+// The state held by a `PreparedState`, ready to be sampled.
+pub(crate) enum Sampler<'a> {
+    Gpu(&'a mut GpuState, GpuDistribution),
+    Stabilizer(AffineDistribution),
+    MatrixProductState(MatrixProductState),
+    Sparse(SparseState),
+}
+
+/// The state obtained by running the gates of a `Program`, returned by `Computer::prepare`, which
+/// can be sampled many times without running the gates again. For the `Backend::StateVector` and
+/// `Backend::DensityMatrix` backends, the reduced distribution of the measured qbits is kept on the
+/// device, which is why the computer is borrowed as long as the prepared state is alive.
+pub struct PreparedState<'a> {
+    duration: Duration,
+    qbits: Box<[Address]>,
+    bases: Box<[Pauli]>,
+    registers: Box<[Register]>,
+    kept_shots: Option<usize>,
+    readout_errors: Box<[(Address, ConfusionMatrix)]>,
+    bit_order: BitOrder,
+    sampler: Sampler<'a>,
+}
+
+impl<'a> PreparedState<'a> {
+    pub(crate) fn new(
+        duration: Duration,
+        program: &Program,
+        readout_errors: Box<[(Address, ConfusionMatrix)]>,
+        bit_order: BitOrder,
+        sampler: Sampler<'a>,
+    ) -> PreparedState<'a> {
+        PreparedState {
+            duration,
+            qbits: program.measured.clone(),
+            bases: program.bases.clone(),
+            registers: program.registers.clone(),
+            kept_shots: program.kept_shots,
+            readout_errors,
+            bit_order,
+            sampler,
+        }
+    }
+
+    /// Returns the time taken to prepare the state.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Returns the addresses of the measured qbits: bit #i of the sampled states is the state of the
+    /// qbit `qbits()[i]`.
+    pub fn qbits(&self) -> &[Address] {
+        &self.qbits
+    }
+
+    /// Performs `samples` measurements of the prepared state, with the readout errors of the
+    /// computer. The pseudo-random number generator is seeded from the `seed` source, a seed or
+    /// `None` for a fresh one, whose seed is recorded in the measurements. The duration of the
+    /// measurements doesn't include the time taken to prepare the state.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `samples` is 0, or if something goes wrong while reading from
+    /// the device.
+    pub fn sample<S>(&mut self, samples: usize, seed: S) -> Measurements
+    where
+        S: Into<SeedSource>,
+    {
+        assert!(
+            samples != 0,
+            "Samples count cannot be 0"
+        );
+
+        self.measure(samples, seed.into().seed(), Instant::now())
+    }
+
+    // Performs `samples` measurements, which started at `start`.
+    pub(crate) fn measure(&mut self, samples: usize, seed: u64, start: Instant) -> Measurements {
+        let prng = Philox::new(seed);
+
+        let mut results = HashMap::with_capacity(samples);
+        let mut readout = Readout::new(&self.readout_errors, &prng, self.kept_shots, self.qbits.len(), samples);
+
+        let all_qbits = self.qbits.iter().enumerate().all(|(i, &qbit)| i == qbit as usize);
+        // Position of the measured qbits in the sampled states
+        let bits: Box<[usize]> = self.qbits.iter().map(|&qbit| qbit as usize).collect();
+
+        // Marginalizes the samples of every qbit taken by the host backends
+        let marginal = |samples: HashMap<BitString, usize>| -> HashMap<BitString, usize> {
+            if all_qbits {
+                return samples;
+            }
+
+            let mut result = HashMap::new();
+            for (state, count) in samples {
+                *result.entry(state.select(&bits)).or_insert(0) += count;
+            }
+            result
+        };
+
+        match &mut self.sampler {
+            Sampler::Gpu(gpu, distribution) => {
+                gpu.sample_distribution(distribution, samples, 0, &prng, &mut results, |state| readout.apply(state));
+            },
+            Sampler::Stabilizer(distribution) => {
+                for shot in 0..samples as u64 {
+                    let mut state = distribution.sample(&mut prng.substream(Stream::Sampling, shot));
+                    if !all_qbits {
+                        state = state.select(&bits);
+                    }
+                    *results.entry(readout.apply(state)).or_insert(0) += 1;
+                }
+            },
+            Sampler::MatrixProductState(mps) => {
+                let mut sampled = HashMap::new();
+                // The samples are drawn together, by splitting them between the states of every site
+                mps.sample(samples, &mut prng.substream(Stream::Sampling, 0), &mut sampled);
+                readout.apply_counts(marginal(sampled), &mut results);
+            },
+            Sampler::Sparse(sparse) => {
+                let mut sampled = HashMap::new();
+                sparse.sample(samples, &prng, &mut sampled);
+                readout.apply_counts(marginal(sampled), &mut results);
+            },
+        }
+
+        let shots = readout.into_shots();
+
+        Measurements::new(
+            Instant::now().duration_since(start),
+            self.qbits.clone(),
+            self.bases.clone(),
+            samples,
+            results,
+            self.registers.clone(),
+            self.bit_order,
+            shots,
+            seed,
+        )
+    }
+
+    /// Returns the exact probability of measuring `state`, bit #i of `state` being the state of the
+    /// qbit `qbits()[i]`, before readout errors. It is computed in single precision for the
+    /// `Backend::StateVector` and `Backend::DensityMatrix` backends.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `state` is not as long as the number of measured qbits, or if
+    /// something goes wrong while reading from the device.
+    pub fn probability(&self, state: &BitString) -> f64 {
+        assert!(
+            state.len() == self.qbits.len(),
+            "The state {:?} is not {} bits long, like the measured states",
+            state,
+            self.qbits.len(),
+        );
+
+        let bits: Box<[usize]> = self.qbits.iter().map(|&qbit| qbit as usize).collect();
+
+        match &self.sampler {
+            Sampler::Gpu(gpu, distribution) => gpu.distribution_probability(distribution, state),
+            Sampler::Stabilizer(distribution) => distribution.probability(&bits, state),
+            Sampler::MatrixProductState(mps) => mps.marginal_probability(&bits, state),
+            Sampler::Sparse(sparse) => sparse.probability(&bits, state),
+        }
+    }
+}
@@ -3,7 +3,12 @@ use std::iter::IntoIterator;
 use std::fmt;
 use std::mem::swap;
 
+use crate::bitstring::BitString;
+use crate::complex::c64;
 use crate::computer::{Address, Computer};
+use crate::gates::{Gate, Pauli};
+use crate::register::Register;
+use crate::state::StateVector;
 
 //#################################################################################################
 //
@@ -11,8 +16,9 @@ use crate::computer::{Address, Computer};
 //
 //#################################################################################################
 
-// Parse a state from a &str. The regex is |[01]{size}> where size is the computer's size.
-fn parse_state(computer: &Computer, state: &str) -> usize {
+// Parse a state from a &str. The regex is |[01]{size}> where size is the computer's size, the
+// digits being written in the computer's bit order.
+fn parse_state(computer: &Computer, state: &str) -> BitString {
     assert!(
         state.len() == computer.size as usize + 2,
         "The given initial state \"{}\" is invalid, it must match \"|[01]{{{}}}>\": the length is invalid", 
@@ -29,14 +35,14 @@ fn parse_state(computer: &Computer, state: &str) -> usize {
         computer.size,
     );
 
-    let mut result = 0;
+    let mut result = BitString::zeros(computer.size as usize);
 
     for i in 0..computer.size {
         let digit = chars.next().unwrap();
 
         match digit {
             '0' => (),
-            '1' => result |= 1usize << i,
+            '1' => result.set(computer.bit_order.index(i as usize, computer.size as usize), true),
             _ => panic!(
                 "The given initial state \"{}\" is invalid, it must match \"|[01]{{{}}}>\": digit #{} is invalid", 
                 state,
@@ -55,6 +61,93 @@ fn parse_state(computer: &Computer, state: &str) -> usize {
 
    result
 }
+
+// Parses a complex number such as "0.6", "-0.8i", "i" or "0.6+0.8i".
+fn parse_complex(text: &str) -> Option<c64> {
+    // Position of the sign separating the real and imaginary parts, if any
+    let split = text.char_indices()
+        .skip(1)
+        .filter(|&(i, c)| (c == '+' || c == '-') && !text[..i].ends_with(['e', 'E']))
+        .map(|(i, _)| i)
+        .last();
+
+    let parse_imaginary = |text: &str| match text {
+        "" | "+" => Some(1.0),
+        "-" => Some(-1.0),
+        _ => text.parse().ok(),
+    };
+
+    match text.strip_suffix('i') {
+        Some(text) => match split {
+            Some(split) => Some(c64::new(text[..split].parse().ok()?, parse_imaginary(&text[split..])?)),
+            None => Some(c64::new(0.0, parse_imaginary(text)?)),
+        },
+        None => Some(c64::new(text.parse().ok()?, 0.0)),
+    }
+}
+
+// Parses a sum of kets with complex coefficients, such as "0.6|00> + 0.8i|11>" or
+// "(1+i)|01> - |10>", and returns the normalized state.
+fn parse_kets(computer: &Computer, text: &str) -> StateVector {
+    let invalid = |reason: &str| -> ! {
+        panic!(
+            "The given initial state \"{}\" is invalid, it must be a sum of kets such as \"0.6|00> + 0.8i|11>\": {}",
+            text,
+            reason,
+        )
+    };
+
+    let chars: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut terms = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let sign = match chars[i] {
+            '+' => {
+                i += 1;
+                1.0
+            },
+            '-' => {
+                i += 1;
+                -1.0
+            },
+            _ if terms.is_empty() => 1.0,
+            c => invalid(&format!("expected '+' or '-' between the terms, found '{}'", c)),
+        };
+
+        let end = chars[i..].iter()
+            .position(|&c| c == '|')
+            .unwrap_or_else(|| invalid("a term has no ket"));
+        let coefficient: String = chars[i..i + end].iter().collect();
+        let coefficient = coefficient.strip_suffix('*').unwrap_or(&coefficient);
+        let coefficient = coefficient.strip_prefix('(')
+            .and_then(|c| c.strip_suffix(')'))
+            .unwrap_or(coefficient);
+
+        let coefficient = if coefficient.is_empty() {
+            c64::ONE
+        } else {
+            parse_complex(coefficient)
+                .unwrap_or_else(|| invalid(&format!("\"{}\" is not a complex number", coefficient)))
+        };
+        i += end;
+
+        let end = chars[i..].iter()
+            .position(|&c| c == '>')
+            .unwrap_or_else(|| invalid("a ket does not end with a '>'"));
+        let ket: String = chars[i..=i + end].iter().collect();
+        i += end + 1;
+
+        terms.push((parse_state(computer, &ket), coefficient * c64::from(sign)));
+    }
+
+    if terms.is_empty() {
+        invalid("the state is empty");
+    }
+
+    StateVector::new(computer.size, terms, true, computer.bit_order)
+}
+
 //#################################################################################################
 //
 //                                      Instruction Type
@@ -71,6 +164,15 @@ pub(crate) struct SingleInstruction<T> {
 
 pub(crate) type Instruction = SingleInstruction<Address>;
 
+// An instruction whose gate has been resolved by the computer, ready to be executed by a backend.
+#[derive(Copy, Clone)]
+pub(crate) struct Operation {
+    pub(crate) gate_name: &'static str,
+    pub(crate) gate: Gate,
+    pub(crate) target: Address,
+    pub(crate) control: Option<Address>,
+}
+
 //#################################################################################################
 //
 //                             InstructionChainInternals trait
@@ -404,6 +506,55 @@ pub struct SubRoutine {
     instructions: Box<[SingleInstruction<char>]>,
 }
 
+//#################################################################################################
+//
+//                                       Initial State
+//
+//#################################################################################################
+
+/// The initial state of a program, given to `Computer::new_program`.
+#[derive(Copy, Clone, Debug)]
+pub enum InitialState<'a> {
+    /// A computational basis state such as `"|010>"`, or a sum of kets with complex coefficients
+    /// such as `"0.6|00> + 0.8i|11>"` or `"(1+i)|01> - |10>"`, which is normalized.
+    Kets(&'a str),
+    /// The `2^size` amplitudes of the state, the amplitude of the basis state `|s>` being at index
+    /// `s` where qbit #i is bit #i of `s`. They are normalized.
+    Amplitudes(&'a [c64]),
+    /// A state vector, usually saved with `Computer::state_vector`.
+    StateVector(&'a StateVector),
+}
+
+impl<'a> From<&'a str> for InitialState<'a> {
+    fn from(kets: &'a str) -> InitialState<'a> {
+        InitialState::Kets(kets)
+    }
+}
+
+impl<'a> From<&'a String> for InitialState<'a> {
+    fn from(kets: &'a String) -> InitialState<'a> {
+        InitialState::Kets(kets)
+    }
+}
+
+impl<'a> From<&'a [c64]> for InitialState<'a> {
+    fn from(amplitudes: &'a [c64]) -> InitialState<'a> {
+        InitialState::Amplitudes(amplitudes)
+    }
+}
+
+impl<'a> From<&'a Vec<c64>> for InitialState<'a> {
+    fn from(amplitudes: &'a Vec<c64>) -> InitialState<'a> {
+        InitialState::Amplitudes(amplitudes)
+    }
+}
+
+impl<'a> From<&'a StateVector> for InitialState<'a> {
+    fn from(state: &'a StateVector) -> InitialState<'a> {
+        InitialState::StateVector(state)
+    }
+}
+
 //#################################################################################################
 //
 //                                       Program Builder
@@ -412,27 +563,64 @@ pub struct SubRoutine {
 
 /// A builder for the `Program` struct.
 pub struct ProgramBuilder<'a> {
-    initial_state: usize,
+    initial_state: StateVector,
     instructions: Vec<Instruction>,
     subroutines: HashMap<&'static str, SubRoutine>,
+    registers: Vec<Register>,
+    kept_shots: Option<usize>,
     computer: &'a Computer,
     measured: bool,
 }
 
 impl<'a> ProgramBuilder<'a> {
-    pub(crate) fn new(computer: &'a Computer, initial_state: &str) -> ProgramBuilder<'a> {
-        let initial_state = parse_state(computer, initial_state);
+    pub(crate) fn new(computer: &'a Computer, initial_state: InitialState) -> ProgramBuilder<'a> {
+        let size = computer.size;
+
+        let initial_state = match initial_state {
+            InitialState::Kets(kets) => parse_kets(computer, kets),
+            InitialState::Amplitudes(amplitudes) => {
+                assert!(
+                    (size as u32) < usize::BITS && amplitudes.len() == 1 << size,
+                    "The initial state has {} amplitudes, but a state of {} qbits has 2^{}",
+                    amplitudes.len(),
+                    size,
+                    size,
+                );
+
+                let amplitudes = amplitudes.iter()
+                    .enumerate()
+                    .map(|(s, &amplitude)| (BitString::from_u64(s as u64, size as usize), amplitude))
+                    .collect();
+                StateVector::new(size, amplitudes, true, computer.bit_order)
+            },
+            InitialState::StateVector(state) => {
+                assert!(
+                    state.size() == size,
+                    "The initial state is a state of {} qbits, but the computer is of size {}",
+                    state.size(),
+                    size,
+                );
+
+                state.with_bit_order(computer.bit_order)
+            },
+        };
 
         let instructions = Vec::new();
 
         let subroutines = HashMap::new();
 
+        let registers = Vec::new();
+
+        let kept_shots = None;
+
         let measured = false;
 
         ProgramBuilder {
             initial_state,
             instructions,
             subroutines,
+            registers,
+            kept_shots,
             computer,
             measured,
         }
@@ -464,17 +652,124 @@ impl<'a> ProgramBuilder<'a> {
         }
     }
 
+    /// Declares a register named `name` made of the next `len` qbits not yet part of a register,
+    /// starting from qbit #0, and returns it.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if there already is a register named `name`, if `len` is 0, or if
+    /// there are less than `len` qbits left.
+    pub fn register(&mut self, name: &'static str, len: Address) -> Register {
+        assert!(
+            !self.registers.iter().any(|register| register.name() == name),
+            "There already exists a register named \"{}\"",
+            name,
+        );
+        assert!(
+            len != 0,
+            "The register \"{}\" cannot be empty",
+            name,
+        );
+
+        let start: usize = self.registers.iter().map(Register::len).sum();
+        assert!(
+            start + len as usize <= self.computer.size as usize,
+            "Cannot declare the {}-qbits register \"{}\", only {} qbits of the {}-sized register are left",
+            len,
+            name,
+            self.computer.size as usize - start,
+            self.computer.size,
+        );
+
+        let register = Register::new(name, start as Address, len);
+        self.registers.push(register.clone());
+        register
+    }
+
+    /// Keeps the outcome of every shot, in the order they were sampled, in addition to the counts:
+    /// see `Measurements::shots`. Only the first `max` shots are kept if it is not `None`, to bound
+    /// the memory used by runs of many samples.
+    /// 
+    /// The `Backend::MatrixProductState` and `Backend::Sparse` backends sample the counts of the
+    /// states directly, their shots are then put in a uniformly random order, which is distributed
+    /// like the order of independent shots.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if `max` is 0.
+    pub fn keep_shots<M>(&mut self, max: M) -> &mut ProgramBuilder<'a>
+    where
+        M: Into<Option<usize>>,
+    {
+        let max = max.into().unwrap_or(usize::MAX);
+        assert!(
+            max != 0,
+            "Cannot keep at most 0 shots",
+        );
+
+        self.kept_shots = Some(max);
+        self
+    }
+
     pub fn measure(&mut self, samples: usize) -> Program {
+        let qbits: Box<[Address]> = (0..self.computer.size).collect();
+        self.measure_qubits(&qbits, samples)
+    }
+
+    /// Ends the program, which will measure each of the given qbits in the eigenbasis of it's Pauli
+    /// operator, the eigenvalue +1 being measured as 0 and -1 as 1. The basis changes are applied
+    /// by the computer after the gates of the program, and are not part of it's instructions.
+    /// Bit #i of the measured states is the outcome of `bases[i]`.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if `samples` is 0, if `bases` is empty, or if one of it's qbits is
+    /// out of the register or is given twice.
+    pub fn measure_in_basis(&mut self, bases: &[(Address, Pauli)], samples: usize) -> Program {
+        let qbits: Box<[Address]> = bases.iter().map(|&(qbit, _)| qbit).collect();
+
+        let mut program = self.measure_qubits(&qbits, samples);
+        program.bases = bases.iter().map(|&(_, pauli)| pauli).collect();
+        program
+    }
+
+    /// Ends the program, which will only measure the given `qbits`: the probabilities of their
+    /// states are summed over the states of the other qbits before sampling, on the device for the
+    /// `Backend::StateVector` and `Backend::DensityMatrix` backends. Bit #i of the measured states
+    /// is the state of `qbits[i]`.
+    /// 
+    /// # Panics
+    /// 
+    /// This function will panic if `samples` is 0, if `qbits` is empty, or if one of it's qbits is
+    /// out of the register or is given twice.
+    pub fn measure_qubits(&mut self, qbits: &[Address], samples: usize) -> Program {
         assert!(
             samples != 0, 
             "Samples count cannot be 0"
         );
+        assert!(
+            !qbits.is_empty(),
+            "Cannot measure an empty set of qbits",
+        );
+        for (i, &qbit) in qbits.iter().enumerate() {
+            assert!(
+                qbit < self.computer.size,
+                "Measured qbit's address (#{}) is out of the {}-sized register",
+                qbit,
+                self.computer.size,
+            );
+            assert!(
+                !qbits[..i].contains(&qbit),
+                "Qbit #{} is measured twice",
+                qbit,
+            );
+        }
 
         let size = self.computer.size;
 
         self.measured = true;
 
-        let initial_state = self.initial_state;
+        let initial_state = self.initial_state.clone();
 
         let instructions = {
             let mut result = Vec::with_capacity(0);
@@ -482,13 +777,25 @@ impl<'a> ProgramBuilder<'a> {
             result.into()
         };
 
+        let registers = self.registers.clone().into();
+
+        let measured = qbits.into();
+
+        let bases = vec![Pauli::Z; qbits.len()].into();
+
         let samples = samples;
 
+        let kept_shots = self.kept_shots;
+
         Program {
             size,
             initial_state,
             instructions,
+            registers,
+            measured,
+            bases,
             samples,
+            kept_shots,
         }
     } 
 }
@@ -551,10 +858,16 @@ impl InstructionChain<Address> for ProgramBuilder<'_> {}
 
 #[derive(Debug)]
 pub struct Program {
-    size: Address,
-    pub(crate) initial_state: usize,
+    pub(crate) size: Address,
+    pub(crate) initial_state: StateVector,
     pub(crate) instructions: Box<[Instruction]>,
+    pub(crate) registers: Box<[Register]>,
+    pub(crate) measured: Box<[Address]>,
+    // The basis in which each measured qbit is measured
+    pub(crate) bases: Box<[Pauli]>,
     pub(crate) samples: usize,
+    // The maximum number of shots kept in order, if any
+    pub(crate) kept_shots: Option<usize>,
 }
 
 impl Program {
@@ -566,11 +879,35 @@ impl Program {
 impl fmt::Display for Program {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, 
-            "[\n  [Program with initial state |{:0size$b}>],\n  [Sample count of {}]",
+            "[\n  [Program with initial state {}],\n  [Sample count of {}]",
             self.initial_state,
-            size = self.size as usize,
+            self.samples,
         ).unwrap();
 
+        if !self.registers.is_empty() {
+            let registers: Vec<String> = self.registers.iter().map(Register::to_string).collect();
+            write!(f, ",\n  [Registers {}]", registers.join(", ")).unwrap();
+        }
+
+        if self.measured.len() != self.size as usize {
+            let qbits: Vec<String> = self.measured.iter().map(|qbit| format!("#{}", qbit)).collect();
+            write!(f, ",\n  [Measured qbits {}]", qbits.join(", ")).unwrap();
+        }
+
+        if self.bases.iter().any(|&pauli| pauli != Pauli::Z) {
+            let bases: Vec<String> = self.measured.iter()
+                .zip(self.bases.iter())
+                .map(|(qbit, pauli)| format!("#{} in {:?}", qbit, pauli))
+                .collect();
+            write!(f, ",\n  [Measurement bases {}]", bases.join(", ")).unwrap();
+        }
+
+        match self.kept_shots {
+            Some(max) if max < self.samples => write!(f, ",\n  [Keeping the first {} shots]", max).unwrap(),
+            Some(_) => write!(f, ",\n  [Keeping every shot]").unwrap(),
+            None => (),
+        }
+
         let len = self.instructions.len();
 
         if len != 0 {
@@ -600,4 +937,74 @@ impl fmt::Display for Program {
             write!(f, "\n]")
         }
     }
-}
\ No newline at end of file
+}
+
+//#################################################################################################
+//
+//                                           Tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitstring::BitOrder;
+    use crate::computer::Backend;
+
+    fn computer(size: Address, bit_order: BitOrder) -> Computer {
+        Computer::new(size)
+            .backend(Backend::Sparse)
+            .bit_order(bit_order)
+            .build()
+    }
+
+    #[test]
+    fn parse_in_both_orders() {
+        let little = computer(4, BitOrder::LittleEndian);
+        let big = computer(4, BitOrder::BigEndian);
+
+        assert_eq!(parse_state(&little, "|0011>"), BitString::from_u64(0b0011, 4));
+        assert_eq!(parse_state(&big, "|0011>"), BitString::from_u64(0b1100, 4));
+    }
+
+    #[test]
+    fn parse_display_round_trip() {
+        for &order in [BitOrder::LittleEndian, BitOrder::BigEndian].iter() {
+            for &size in [5, 64, 100].iter() {
+                let computer = computer(size, order);
+
+                let mut state = BitString::zeros(size as usize);
+                for i in (0..size as usize).filter(|i| i % 3 == 0 || *i == size as usize - 1) {
+                    state.set(i, true);
+                }
+
+                let ket = format!("|{}>", state.display(order));
+                assert_eq!(parse_state(&computer, &ket), state, "Parsing {} in {:?}", ket, order);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_wide() {
+        let computer = computer(100, BitOrder::LittleEndian);
+        let ket = format!("|1{}1>", "0".repeat(98));
+
+        let state = parse_state(&computer, &ket);
+        assert_eq!(state.count_ones(), 2);
+        assert!(state.get(0) && state.get(99));
+    }
+
+    #[test]
+    #[should_panic(expected = "digit #3 is invalid")]
+    fn parse_invalid_digit() {
+        parse_state(&computer(4, BitOrder::LittleEndian), "|0020>");
+    }
+
+    #[test]
+    fn parse_kets_in_both_orders() {
+        let state = parse_kets(&computer(3, BitOrder::BigEndian), "0.6|100> - 0.8i|011>");
+
+        assert!((state.probability(&BitString::from_u64(0b001, 3)) - 0.36).abs() < 1e-6);
+        assert!((state.probability(&BitString::from_u64(0b110, 3)) - 0.64).abs() < 1e-6);
+    }
+}
@@ -1,79 +1,308 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::SystemTime;
 
-pub(crate) union MWC64X {
-    vector: (u32, u32),
-    scalar: u64,
+//#################################################################################################
+//
+//                                        Seed source
+//
+//#################################################################################################
+
+/// The seed of the pseudo-random number generator of a run, passed to `Computer::run` and
+/// `PreparedState::sample`. The seed used is recorded in the `Measurements`, so that any run can be
+/// reproduced.
+///
+/// `None`, `Some(seed)` and plain seeds convert into it, as well as, with the `rand_core` feature,
+/// any `&mut RngCore` from which the seed is drawn.
+///
+/// ```
+/// use trident::SeedSource;
+///
+/// assert_eq!(SeedSource::from(42), SeedSource::Seed(42));
+/// assert_eq!(SeedSource::from(None), SeedSource::Entropy);
+/// ```
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SeedSource {
+    /// A fresh seed drawn from the entropy of the operating system, different for every run. This
+    /// is the default.
+    #[default]
+    Entropy,
+    /// The given seed, to recreate the results of a previous run.
+    Seed(u64),
+}
+
+impl SeedSource {
+    /// Returns the seed of the source, drawing a fresh one for `SeedSource::Entropy`.
+    pub fn seed(self) -> u64 {
+        match self {
+            SeedSource::Entropy => {
+                // The keys of the hashers are drawn from the operating system's entropy, the
+                // counter and time only tell apart the seeds drawn with the same keys
+                static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+                let mut hasher = RandomState::new().build_hasher();
+                COUNTER.fetch_add(1, Ordering::Relaxed).hash(&mut hasher);
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .expect("Duration since UNIX_EPOCH failed")
+                    .as_nanos()
+                    .hash(&mut hasher);
+                hasher.finish()
+            },
+            SeedSource::Seed(seed) => seed,
+        }
+    }
 }
 
-impl MWC64X {
-    pub(crate) fn new(seed: Option<u64>) -> MWC64X {
-        let seed = match seed {
-            Some(s) => s,
-            None => SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .expect("Duration since UNIX_EPOCH failed")
-                .as_secs(),
-        };
-        
-        MWC64X { scalar: seed ^ 0x8CCC1D021231BBAC}
+impl From<u64> for SeedSource {
+    fn from(seed: u64) -> SeedSource {
+        SeedSource::Seed(seed)
     }
+}
+
+impl From<Option<u64>> for SeedSource {
+    fn from(seed: Option<u64>) -> SeedSource {
+        match seed {
+            Some(seed) => SeedSource::Seed(seed),
+            None => SeedSource::Entropy,
+        }
+    }
+}
+
+#[cfg(feature = "rand_core")]
+impl<R: rand_core::RngCore + ?Sized> From<&mut R> for SeedSource {
+    /// Draws the seed from the `rng`.
+    fn from(rng: &mut R) -> SeedSource {
+        SeedSource::Seed(rng.next_u64())
+    }
+}
+
+//#################################################################################################
+//
+//                                          Streams
+//
+//#################################################################################################
 
-    pub(crate) fn state(&self) -> u64 {
-        unsafe { self.scalar }
+// The independent streams of random numbers of a run, each one made of substreams identified by a
+// 64 bits index. The values are part of the counters, they are shared with the OpenCL kernels.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Stream {
+    // The outcome of every shot, the substream being the index of the shot
+    Sampling = 0,
+    // The readout errors of every shot, the substream being the index of the shot
+    Readout = 1,
+    // The channels of the noise model applied in every trajectory, the substream being the index
+    // of the trajectory
+    Noise = 2,
+    // The order of the shots sampled as counts by the host backends
+    Order = 3,
+}
+
+//#################################################################################################
+//
+//                                        Philox4x32-10
+//
+//#################################################################################################
+
+// Returns the block of random numbers of the `counter` with the `key`, with the 10 rounds of the
+// Philox4x32 function of Salmon et al.
+#[inline]
+fn philox(mut counter: [u32; 4], mut key: [u32; 2]) -> [u32; 4] {
+    const M0: u64 = 0xD2511F53;
+    const M1: u64 = 0xCD9E8D57;
+    const W0: u32 = 0x9E3779B9;
+    const W1: u32 = 0xBB67AE85;
+
+    for _ in 0..10 {
+        let p0 = M0 * counter[0] as u64;
+        let p1 = M1 * counter[2] as u64;
+
+        counter = [
+            (p1 >> 32) as u32 ^ counter[1] ^ key[0],
+            p1 as u32,
+            (p0 >> 32) as u32 ^ counter[3] ^ key[1],
+            p0 as u32,
+        ];
+
+        key = [key[0].wrapping_add(W0), key[1].wrapping_add(W1)];
+    }
+
+    counter
+}
+
+// A counter-based pseudo-random number generator: the numbers are the blocks of Philox4x32-10 of
+// the counters (i, substream, stream), for i = 0, 1, ..., the key being derived from the seed. Any
+// substream can be drawn independently of the others, on the host as well as on the device.
+#[derive(Clone, Debug)]
+pub(crate) struct Philox {
+    key: [u32; 2],
+    counter: [u32; 4],
+    block: [u32; 4],
+    // Index of the next unused number of the block
+    index: usize,
+}
+
+impl Philox {
+    // Creates the generator of the seed. Numbers are drawn from it's substreams.
+    pub(crate) fn new(seed: u64) -> Philox {
+        let seed = seed ^ 0x8CCC1D021231BBAC;
+
+        Philox {
+            key: [seed as u32, (seed >> 32) as u32],
+            counter: [0; 4],
+            block: [0; 4],
+            index: 4,
+        }
     }
 
-    pub(crate) fn skip(&mut self, distance: u64) {
-        // a < m && b < m -> r = (a+b) % m
-        #[inline]
-        fn modular_add64(a: u64, b: u64, m: u64) -> u64 {
-            let mut res = a + b;
+    // Returns the key, as the `uint2` expected by the kernels.
+    pub(crate) fn key(&self) -> u64 {
+        self.key[0] as u64 | (self.key[1] as u64) << 32
+    }
 
-            if res >= m || res < a {
-                res -= m;
-            }
+    // Returns the generator of the #substream substream of the `stream`, with the same key.
+    pub(crate) fn substream(&self, stream: Stream, substream: u64) -> Philox {
+        Philox {
+            key: self.key,
+            counter: [0, substream as u32, (substream >> 32) as u32, stream as u32],
+            block: [0; 4],
+            index: 4,
+        }
+    }
 
-            res
+    // Returns a random integer and advances the generator by one step.
+    pub(crate) fn next_u32(&mut self) -> u32 {
+        if self.index == 4 {
+            self.block = philox(self.counter, self.key);
+            self.counter[0] = self.counter[0].wrapping_add(1);
+            self.index = 0;
         }
-        
-        // a < m && b < m -> (a*b) % m
-        #[inline]
-        fn modular_mul64(mut a: u64, mut b: u64, m: u64) -> u64 {
-            let mut res = 0;
-
-            while a != 0 {
-                if a & 1 != 0 {
-                    res = modular_add64(res, b, m);
-                }
-                b = modular_add64(b, b, m);
-                a >>= 1;
-            }
-
-            res
+
+        self.index += 1;
+        self.block[self.index - 1]
+    }
+
+    // Returns a random float from [0, 1) and advances the generator by one step.
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        self.next_u32() as f64 * 2.3283064365386963e-10
+    }
+}
+
+//#################################################################################################
+//
+//                                           Tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::computer::{Backend, Computer};
+    use crate::program::InstructionChain;
+    use crate::readout::ConfusionMatrix;
+    use crate::statistics::chi_squared_survival;
+
+    // Returns the first `n` integers of the #substream substream of the `stream`.
+    fn draw(prng: &Philox, stream: Stream, substream: u64, n: usize) -> Vec<u32> {
+        let mut prng = prng.substream(stream, substream);
+        (0..n).map(|_| prng.next_u32()).collect()
+    }
+
+    #[test]
+    fn known_answers() {
+        // The known answer tests of Random123
+        assert_eq!(philox([0; 4], [0; 2]), [0x6627e8d5, 0xe169c58d, 0xbc57ac4c, 0x9b00dbd8]);
+        assert_eq!(philox([u32::MAX; 4], [u32::MAX; 2]), [0x408f276d, 0x41c83b0e, 0xa20bc7c6, 0x6d5451fd]);
+        assert_eq!(
+            philox([0x243f6a88, 0x85a308d3, 0x13198a2e, 0x03707344], [0xa4093822, 0x299f31d0]),
+            [0xd16cfe09, 0x94fdcceb, 0x5001e420, 0x24126ea1],
+        );
+    }
+
+    #[test]
+    fn counters() {
+        let prng = Philox::new(42);
+        let key = [prng.key() as u32, (prng.key() >> 32) as u32];
+
+        // The numbers of a substream are the blocks of the successive counters
+        let substream = 0x1234_5678_9abc_def0;
+        let expected: Vec<u32> = (0..3)
+            .flat_map(|i| philox([i, substream as u32, (substream >> 32) as u32, Stream::Noise as u32], key).to_vec())
+            .collect();
+        assert_eq!(draw(&prng, Stream::Noise, substream, 12), expected);
+
+        // The seed is the key, up to a constant
+        assert_eq!(prng.key(), 42 ^ 0x8CCC1D021231BBAC);
+    }
+
+    #[test]
+    fn uniformity() {
+        const BINS: usize = 100;
+        const DRAWS: usize = 100_000;
+
+        let mut prng = Philox::new(0).substream(Stream::Sampling, 0);
+        let mut counts = [0usize; BINS];
+        for _ in 0..DRAWS {
+            let x = prng.next_f64();
+            assert!((0.0..1.0).contains(&x));
+            counts[(x * BINS as f64) as usize] += 1;
         }
-        
-        // a < m && e < m -> (a**e) % m
-        #[inline]
-        fn modular_pow64(a: u64, mut e: u64, m: u64) -> u64 {
-            let (mut sqr, mut acc) = (a, 1);
-
-            while e != 0 {
-                if e & 1 != 0 {
-                    acc = modular_mul64(acc, sqr, m);
-                }
-                sqr = modular_mul64(sqr, sqr, m);
-                e >>= 1;
-            }
-
-            acc
+
+        let expected = (DRAWS / BINS) as f64;
+        let statistic: f64 = counts.iter()
+            .map(|&count| (count as f64 - expected) * (count as f64 - expected) / expected)
+            .sum();
+        let p_value = chi_squared_survival(statistic, BINS - 1);
+        assert!(p_value > 1e-3, "The chi-squared statistic {} has a p-value of {}", statistic, p_value);
+    }
+
+    #[test]
+    fn independent_substreams() {
+        let prng = Philox::new(7);
+        let first = draw(&prng, Stream::Sampling, 0, 1000);
+
+        // Other substreams, streams and seeds give other numbers
+        for other in [
+            draw(&prng, Stream::Sampling, 1, 1000),
+            draw(&prng, Stream::Sampling, 1 << 32, 1000),
+            draw(&prng, Stream::Readout, 0, 1000),
+            draw(&Philox::new(8), Stream::Sampling, 0, 1000),
+        ].iter() {
+            let equal = first.iter().zip(other.iter()).filter(|(x, y)| x == y).count();
+            assert!(equal <= 1, "{} equal numbers in two substreams", equal);
         }
 
-        const A: u64 = 0xFFFEB81B;
-        const M: u64 = 0xFFFEB81AFFFFFFFF;
+        // The first numbers of neighbouring substreams are not correlated
+        let n = 10000;
+        let x: Vec<f64> = (0..=n).map(|i| prng.substream(Stream::Sampling, i).next_f64()).collect();
+        let mean = x.iter().sum::<f64>() / x.len() as f64;
+        let variance = x.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / x.len() as f64;
+        let covariance = x.windows(2).map(|w| (w[0] - mean) * (w[1] - mean)).sum::<f64>() / n as f64;
 
-        let m = modular_pow64(A, distance, M);
-        let state = unsafe { self.vector };
-        let mut x = state.0 as u64 * A + state.1 as u64;
-        x = modular_mul64(x, m, M);
-        self.vector = ((x / A) as u32, (x % A) as u32);
+        // The correlation of independent samples is about N(0, 1/n)
+        assert!((covariance / variance).abs() < 4.0 / (n as f64).sqrt());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn shots_of_substreams() {
+        let mut computer = Computer::new(3)
+            .add_default_gates()
+            .backend(Backend::Stabilizer)
+            .readout_error(0, ConfusionMatrix::new(0.1, 0.2))
+            .build();
+        let mut program = computer.new_program("|000>");
+        program.apply_iter("H", 0..3, None);
+        let program = program.keep_shots(None).measure(1000);
+        let mut prepared = computer.prepare(&program);
+
+        // The shot #i is sampled and read out from the substreams #i, whatever the number of
+        // shots drawn
+        let shots = prepared.sample(1000, 1234).integer_shots(0..3, None);
+        let first = prepared.sample(300, 1234).integer_shots(0..3, None);
+        assert_eq!(first[..], shots[..300]);
+
+        let other = prepared.sample(300, 1235).integer_shots(0..3, None);
+        assert_ne!(other[..], shots[..300]);
+    }
+}
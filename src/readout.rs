@@ -0,0 +1,368 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::bitstring::BitString;
+
+use crate::computer::Address;
+use crate::measure::Measurements;
+use crate::random::Philox;
+
+//#################################################################################################
+//
+//                                     Helper functions
+//
+//#################################################################################################
+
+// Applies the tensor product of the 2x2 `matrices`, the one at index i acting on qbit #i, to the
+// sparse vector `vector`. Entries smaller than `1e-12` are dropped.
+pub(crate) fn apply_tensor(
+    vector: HashMap<BitString, f64>,
+    matrices: &[[[f64; 2]; 2]],
+) -> HashMap<BitString, f64> {
+    matrices.iter()
+        .enumerate()
+        .fold(vector, |vector, (qbit, m)| {
+            let mut result = HashMap::with_capacity(2 * vector.len());
+
+            for (mut state, x) in vector {
+                let bit = state.get(qbit) as usize;
+
+                state.set(qbit, false);
+                *result.entry(state.clone()).or_insert(0.0) += m[0][bit] * x;
+                state.set(qbit, true);
+                *result.entry(state).or_insert(0.0) += m[1][bit] * x;
+            }
+
+            result.retain(|_, x: &mut f64| x.abs() > 1e-12);
+            result
+        })
+}
+
+// Returns the euclidian projection of `vector` onto the probability simplex.
+pub(crate) fn project_simplex<K: Eq + Hash>(vector: &mut HashMap<K, f64>) {
+    let mut sorted: Vec<f64> = vector.values().copied().collect();
+    sorted.sort_by(|a, b| b.partial_cmp(a).unwrap());
+
+    let mut sum = 0.0;
+    let mut theta = 0.0;
+    for (i, x) in sorted.iter().enumerate() {
+        sum += x;
+        let t = (sum - 1.0) / (i + 1) as f64;
+        if x - t > 0.0 {
+            theta = t;
+        }
+    }
+
+    for x in vector.values_mut() {
+        *x = (*x - theta).max(0.0);
+    }
+    vector.retain(|_, x| *x > 0.0);
+}
+
+//#################################################################################################
+//
+//                                      Confusion matrix
+//
+//#################################################################################################
+
+/// The readout confusion matrix of a single qbit:
+///
+/// ```math
+///     ┌                      ┐
+/// C = │  P(0|0)    P(0|1)    │
+///     │  P(1|0)    P(1|1)    │
+///     └                      ┘
+/// ```
+///
+/// where `P(r|s)` is the probability of reading `r` when the qbit is in state `|s>`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ConfusionMatrix {
+    p01: f64,
+    p10: f64,
+}
+
+impl ConfusionMatrix {
+    /// Creates a new confusion matrix: `p01` is the probability of reading a `1` when the qbit is in
+    /// state `|0>`, and `p10` the probability of reading a `0` when it is in state `|1>`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `p01` or `p10` is not a probability.
+    pub fn new(p01: f64, p10: f64) -> ConfusionMatrix {
+        assert!(
+            (0.0..=1.0).contains(&p01) && (0.0..=1.0).contains(&p10),
+            "The readout error probabilities ({}, {}) are not in [0, 1]",
+            p01,
+            p10,
+        );
+
+        ConfusionMatrix {
+            p01,
+            p10,
+        }
+    }
+
+    /// The confusion matrix of a perfect readout, that is the identity.
+    pub fn ideal() -> ConfusionMatrix {
+        ConfusionMatrix::new(0.0, 0.0)
+    }
+
+    /// Estimates the confusion matrices of every qbit from two calibration runs: `zeros` sampled
+    /// from the state `|00...0>` and `ones` sampled from the state `|11...1>`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the two measurements have a different number of qbits.
+    pub fn calibrate(zeros: &Measurements, ones: &Measurements) -> Box<[ConfusionMatrix]> {
+        assert!(
+            zeros.size() == ones.size(),
+            "The calibration measurements have different sizes: {} and {}",
+            zeros.size(),
+            ones.size(),
+        );
+
+        let flipped = |measurements: &Measurements, qbit: Address, value: bool| {
+            let count: usize = measurements.counts()
+                .filter(|(state, _)| state.get(qbit as usize) != value)
+                .map(|(_, count)| count)
+                .sum();
+            count as f64 / measurements.samples() as f64
+        };
+
+        (0..zeros.size())
+            .map(|qbit| ConfusionMatrix::new(flipped(zeros, qbit, false), flipped(ones, qbit, true)))
+            .collect()
+    }
+
+    /// Returns the probability of reading a `1` when the qbit is in state `|0>`.
+    pub fn p01(&self) -> f64 {
+        self.p01
+    }
+
+    /// Returns the probability of reading a `0` when the qbit is in state `|1>`.
+    pub fn p10(&self) -> f64 {
+        self.p10
+    }
+
+    // Returns the matrix as rows.
+    #[inline]
+    pub(crate) fn matrix(&self) -> [[f64; 2]; 2] {
+        [
+            [1.0 - self.p01, self.p10],
+            [self.p01, 1.0 - self.p10],
+        ]
+    }
+
+    // Returns the inverse of the matrix as rows.
+    pub(crate) fn inverse(&self) -> [[f64; 2]; 2] {
+        let det = 1.0 - self.p01 - self.p10;
+
+        assert!(
+            det.abs() > 1e-9,
+            "The confusion matrix with p01 = {} and p10 = {} is not invertible",
+            self.p01,
+            self.p10,
+        );
+
+        [
+            [(1.0 - self.p10) / det, -self.p10 / det],
+            [-self.p01 / det, (1.0 - self.p01) / det],
+        ]
+    }
+
+    // Randomly flips the bit #qbit of the measured `state`.
+    #[inline]
+    pub(crate) fn apply(&self, state: &mut BitString, qbit: Address, prng: &mut Philox) {
+        let qbit = qbit as usize;
+        let p = if state.get(qbit) { self.p10 } else { self.p01 };
+
+        if p != 0.0 && prng.next_f64() < p {
+            state.flip(qbit);
+        }
+    }
+}
+
+//#################################################################################################
+//
+//                                         Mitigation
+//
+//#################################################################################################
+
+/// The method used to mitigate readout errors.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mitigation {
+    /// Applies the inverse of the tensor product of the confusion matrices to the measured
+    /// frequencies. The result is a quasi-probability distribution: it sums to `1`, but may contain
+    /// negative values.
+    TensorInversion,
+    /// Finds the probability distribution minimizing the euclidian distance between the measured
+    /// frequencies and the distribution transformed by the confusion matrices.
+    LeastSquares,
+}
+
+//#################################################################################################
+//
+//                                           Tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::bitstring::BitOrder;
+    use crate::gates::Pauli;
+
+    // The measurements of `samples` shots of `size` qbits, distributed as the `probabilities`.
+    fn measurements(size: usize, samples: usize, probabilities: &HashMap<BitString, f64>) -> Measurements {
+        let counts = probabilities.iter()
+            .map(|(state, p)| (state.clone(), (p * samples as f64).round() as usize))
+            .collect();
+
+        Measurements::new(
+            Duration::default(),
+            (0..size as Address).collect(),
+            vec![Pauli::Z; size].into_boxed_slice(),
+            samples,
+            counts,
+            Box::new([]),
+            BitOrder::LittleEndian,
+            None,
+            0,
+        )
+    }
+
+    fn state(value: u64, len: usize) -> BitString {
+        BitString::from_u64(value, len)
+    }
+
+    #[test]
+    fn inverse_round_trip() {
+        for &(p01, p10) in [(0.0, 0.0), (0.1, 0.2), (0.3, 0.05), (0.6, 0.7)].iter() {
+            let confusion = ConfusionMatrix::new(p01, p10);
+            let (m, inverse) = (confusion.matrix(), confusion.inverse());
+            let columns = [[inverse[0][0], inverse[1][0]], [inverse[0][1], inverse[1][1]]];
+
+            for (i, row) in m.iter().enumerate() {
+                for (j, column) in columns.iter().enumerate() {
+                    let product = row[0] * column[0] + row[1] * column[1];
+                    let expected = if i == j { 1.0 } else { 0.0 };
+                    assert!((product - expected).abs() < 1e-12, "C * C^-1 = {} at ({}, {})", product, i, j);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn singular_matrix() {
+        ConfusionMatrix::new(0.5, 0.5).inverse();
+    }
+
+    #[test]
+    fn tensor_round_trip() {
+        let matrices: Box<[_]> = [(0.1, 0.2), (0.05, 0.15), (0.2, 0.0)].iter()
+            .map(|&(p01, p10)| ConfusionMatrix::new(p01, p10))
+            .collect();
+        let forward: Box<[_]> = matrices.iter().map(ConfusionMatrix::matrix).collect();
+        let backward: Box<[_]> = matrices.iter().map(ConfusionMatrix::inverse).collect();
+
+        let vector: HashMap<BitString, f64> = vec![(state(0b000, 3), 0.25), (state(0b101, 3), 0.75)]
+            .into_iter()
+            .collect();
+        let result = apply_tensor(apply_tensor(vector.clone(), &forward), &backward);
+
+        assert_eq!(result.len(), vector.len());
+        for (state, x) in vector.iter() {
+            assert!((result[state] - x).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn simplex_projection() {
+        // Already a distribution
+        let mut vector: HashMap<u8, f64> = vec![(0, 0.25), (1, 0.75)].into_iter().collect();
+        project_simplex(&mut vector);
+        assert!((vector[&0] - 0.25).abs() < 1e-12 && (vector[&1] - 0.75).abs() < 1e-12);
+
+        // Shifted equally towards the simplex
+        let mut vector: HashMap<u8, f64> = vec![(0, 0.6), (1, 0.6)].into_iter().collect();
+        project_simplex(&mut vector);
+        assert!((vector[&0] - 0.5).abs() < 1e-12 && (vector[&1] - 0.5).abs() < 1e-12);
+
+        // Negative values are dropped
+        let mut vector: HashMap<u8, f64> = vec![(0, 4.0 / 3.0), (1, -1.0 / 3.0)].into_iter().collect();
+        project_simplex(&mut vector);
+        assert_eq!(vector.len(), 1);
+        assert!((vector[&0] - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn calibration() {
+        let zeros = measurements(2, 1000, &vec![(state(0b00, 2), 0.9), (state(0b01, 2), 0.1)].into_iter().collect());
+        let ones = measurements(2, 1000, &vec![(state(0b11, 2), 0.8), (state(0b01, 2), 0.2)].into_iter().collect());
+
+        let confusion = ConfusionMatrix::calibrate(&zeros, &ones);
+
+        assert!((confusion[0].p01() - 0.1).abs() < 1e-12 && confusion[0].p10().abs() < 1e-12);
+        assert!(confusion[1].p01().abs() < 1e-12 && (confusion[1].p10() - 0.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn tensor_inversion_recovers_distribution() {
+        let confusion = [ConfusionMatrix::new(0.1, 0.2), ConfusionMatrix::new(0.05, 0.1)];
+        let matrices: Box<[_]> = confusion.iter().map(ConfusionMatrix::matrix).collect();
+
+        // A GHZ-like distribution, confused exactly into the measured frequencies
+        let original: HashMap<BitString, f64> = vec![(state(0b00, 2), 0.5), (state(0b11, 2), 0.5)]
+            .into_iter()
+            .collect();
+        let measured = measurements(2, 100000, &apply_tensor(original.clone(), &matrices));
+
+        let mitigated = measured.mitigate_readout(&confusion, Mitigation::TensorInversion);
+
+        for value in 0..4 {
+            let state = state(value, 2);
+            let expected = original.get(&state).copied().unwrap_or(0.0);
+            let actual = mitigated.get(&state).copied().unwrap_or(0.0);
+            assert!((actual - expected).abs() < 1e-9, "P({:?}) = {}, expected {}", state, actual, expected);
+        }
+    }
+
+    #[test]
+    fn least_squares_on_simplex() {
+        // Reading only zeros is impossible with these readout errors: the inversion is negative
+        let confusion = [ConfusionMatrix::new(0.2, 0.2)];
+        let measured = measurements(1, 1000, &vec![(state(0, 1), 1.0)].into_iter().collect());
+
+        let inverted = measured.mitigate_readout(&confusion, Mitigation::TensorInversion);
+        assert!(inverted[&state(1, 1)] < 0.0);
+        assert!((inverted.values().sum::<f64>() - 1.0).abs() < 1e-12);
+
+        let mitigated = measured.mitigate_readout(&confusion, Mitigation::LeastSquares);
+        assert!(mitigated.values().all(|&p| p >= 0.0));
+        assert!((mitigated.values().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!((mitigated[&state(0, 1)] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn least_squares_recovers_distribution() {
+        let confusion = [ConfusionMatrix::new(0.1, 0.2), ConfusionMatrix::new(0.05, 0.1)];
+        let matrices: Box<[_]> = confusion.iter().map(ConfusionMatrix::matrix).collect();
+
+        let original: HashMap<BitString, f64> = vec![(state(0b01, 2), 0.3), (state(0b10, 2), 0.7)]
+            .into_iter()
+            .collect();
+        let measured = measurements(2, 100000, &apply_tensor(original.clone(), &matrices));
+
+        let mitigated = measured.mitigate_readout(&confusion, Mitigation::LeastSquares);
+
+        for value in 0..4 {
+            let state = state(value, 2);
+            let expected = original.get(&state).copied().unwrap_or(0.0);
+            let actual = mitigated.get(&state).copied().unwrap_or(0.0);
+            assert!((actual - expected).abs() < 1e-6, "P({:?}) = {}, expected {}", state, actual, expected);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::fmt;
+use std::iter::Copied;
+use std::ops::Index;
+use std::slice::Iter;
+
+use crate::bitstring::{BitOrder, BitString};
+use crate::computer::Address;
+
+//#################################################################################################
+//
+//                                          Register
+//
+//#################################################################################################
+
+/// A named group of consecutive qbits, declared with `ProgramBuilder::register`.
+///
+/// `register[i]` is the address of it's qbit #i, and iterating over `&register` yields the
+/// addresses of all it's qbits, so that it can be given to `apply_iter`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Register {
+    name: &'static str,
+    addresses: Box<[Address]>,
+}
+
+impl Register {
+    // Creates the register `name` of the `len` qbits starting at `start`.
+    pub(crate) fn new(name: &'static str, start: Address, len: Address) -> Register {
+        Register {
+            name,
+            addresses: (start..start + len).collect(),
+        }
+    }
+
+    /// Returns the name of the register.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the number of qbits of the register.
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    /// Returns true if the register has no qbits.
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+
+    /// Returns an iterator over the addresses of the qbits of the register.
+    pub fn iter(&self) -> Copied<Iter<'_, Address>> {
+        self.addresses.iter().copied()
+    }
+
+    /// Decodes the value of the register in the measured `state` as an unsigned integer, it's qbits
+    /// being read in the given `order`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the register is wider than 64 qbits, or if it's qbits are out of
+    /// the `state`.
+    pub fn decode(&self, state: &BitString, order: BitOrder) -> u64 {
+        assert!(
+            self.len() <= 64,
+            "The register \"{}\" has {} qbits, it cannot be decoded in 64 bits",
+            self.name,
+            self.len(),
+        );
+
+        let bits: Vec<usize> = self.iter().map(usize::from).collect();
+        order.decode(state, &bits)
+    }
+}
+
+impl Index<usize> for Register {
+    type Output = Address;
+
+    fn index(&self, i: usize) -> &Address {
+        assert!(
+            i < self.len(),
+            "Qbit #{} is out of the {}-qbits register \"{}\"",
+            i,
+            self.len(),
+            self.name,
+        );
+
+        &self.addresses[i]
+    }
+}
+
+impl<'a> IntoIterator for &'a Register {
+    type Item = Address;
+    type IntoIter = Copied<Iter<'a, Address>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.addresses.first(), self.addresses.last()) {
+            (Some(first), Some(last)) => write!(f, "{}[#{}..=#{}]", self.name, first, last),
+            _ => write!(f, "{}[]", self.name),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use crate::random::Philox;
+
+//#################################################################################################
+//
+//                                      Sampling method
+//
+//#################################################################################################
+
+/// The way the `Backend::StateVector` and `Backend::DensityMatrix` backends draw samples from the
+/// distribution of the measured states, set with `ComputerBuilder::sampling`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Sampling {
+    /// Every sample is drawn on the device, by walking down the tree of the partial sums of the
+    /// probabilities, in blocks of `ComputerBuilder::measurement_block` samples. Nothing but the
+    /// samples is read back from the device, which suits distributions of many states.
+    Tree,
+    /// The probabilities are read back from the device once, to build an alias table from which
+    /// every sample is drawn on the host in constant time. It suits distributions of few states
+    /// sampled many times.
+    AliasTable,
+    /// `Sampling::AliasTable` if there are at least as many samples as measurable states, and
+    /// `Sampling::Tree` otherwise. This is the default.
+    Auto,
+}
+
+impl Sampling {
+    // Returns true if an alias table should be used to draw `samples` samples from a distribution
+    // of `states` states.
+    pub(crate) fn alias_table(self, states: usize, samples: usize) -> bool {
+        match self {
+            Sampling::Tree => false,
+            Sampling::AliasTable => true,
+            Sampling::Auto => samples >= states,
+        }
+    }
+}
+
+//#################################################################################################
+//
+//                                        Alias table
+//
+//#################################################################################################
+
+// Walker's alias table of a discrete distribution, built with Vose's method: column #i is kept
+// with probability probabilities[i], and replaced by aliases[i] otherwise.
+pub(crate) struct AliasTable {
+    probabilities: Box<[f64]>,
+    aliases: Box<[usize]>,
+}
+
+impl AliasTable {
+    // Creates the alias table of the distribution proportional to the `weights`.
+    pub(crate) fn new(weights: &[f64]) -> AliasTable {
+        let len = weights.len();
+        let total: f64 = weights.iter().map(|w| w.max(0.0)).sum();
+        assert!(total > 0.0, "Cannot sample a distribution without any positive weight");
+
+        let mut probabilities: Vec<f64> = weights.iter()
+            .map(|w| w.max(0.0) * len as f64 / total)
+            .collect();
+        let mut aliases: Vec<usize> = (0..len).collect();
+
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) = (0..len).partition(|&i| probabilities[i] < 1.0);
+
+        while let (Some(&s), Some(&l)) = (small.last(), large.last()) {
+            small.pop();
+            aliases[s] = l;
+            probabilities[l] -= 1.0 - probabilities[s];
+
+            if probabilities[l] < 1.0 {
+                large.pop();
+                small.push(l);
+            }
+        }
+
+        // The remaining columns are full, up to rounding errors
+        for i in small.into_iter().chain(large) {
+            probabilities[i] = 1.0;
+        }
+
+        AliasTable {
+            probabilities: probabilities.into(),
+            aliases: aliases.into(),
+        }
+    }
+
+    // Draws the index of one state of the distribution.
+    #[inline]
+    pub(crate) fn sample(&self, prng: &mut Philox) -> usize {
+        let column = ((prng.next_u32() as u64 * self.probabilities.len() as u64) >> 32) as usize;
+
+        if prng.next_f64() < self.probabilities[column] {
+            column
+        } else {
+            self.aliases[column]
+        }
+    }
+}
+
+//#################################################################################################
+//
+//                                           Tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The probability of drawing every state of the table, summed over the columns.
+    fn masses(table: &AliasTable) -> Vec<f64> {
+        let len = table.probabilities.len();
+        let mut masses = vec![0.0; len];
+
+        for (column, &alias) in table.aliases.iter().enumerate() {
+            masses[column] += table.probabilities[column] / len as f64;
+            masses[alias] += (1.0 - table.probabilities[column]) / len as f64;
+        }
+
+        masses
+    }
+
+    #[test]
+    fn normalized_weights() {
+        let weights = [1.0, 4.0, 0.5, 2.5, 2.0];
+        let masses = masses(&AliasTable::new(&weights));
+
+        for (mass, weight) in masses.iter().zip(weights) {
+            assert!((mass - weight / 10.0).abs() < 1e-12, "{} != {}", mass, weight / 10.0);
+        }
+    }
+
+    #[test]
+    fn single_state() {
+        let table = AliasTable::new(&[0.3]);
+        let mut prng = Philox::new(0);
+
+        assert_eq!(masses(&table), [1.0]);
+        assert!((0..100).all(|_| table.sample(&mut prng) == 0));
+    }
+
+    #[test]
+    fn zero_weight() {
+        let table = AliasTable::new(&[0.5, 0.0, 0.5, 0.0]);
+        let mut prng = Philox::new(0);
+
+        assert_eq!(masses(&table), [0.5, 0.0, 0.5, 0.0]);
+        assert!((0..1000).all(|_| [0, 2].contains(&table.sample(&mut prng))));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot sample a distribution without any positive weight")]
+    fn no_positive_weight() {
+        AliasTable::new(&[0.0, -1.0]);
+    }
+}
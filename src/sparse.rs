@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+
+use crate::bitstring::BitString;
+use crate::complex::c128;
+use crate::computer::Address;
+use crate::program::Operation;
+use crate::random::{Philox, Stream};
+use crate::state::StateVector;
+
+//#################################################################################################
+//
+//                                       Sparse State
+//
+//#################################################################################################
+
+// A state vector storing only it's nonzero amplitudes.
+pub(crate) struct SparseState {
+    size: Address,
+    amplitudes: HashMap<BitString, c128>,
+    max_support: usize,
+}
+
+impl SparseState {
+    // Creates the state `initial_state`, whose support may grow up to `max_support` basis states.
+    pub(crate) fn new(size: Address, initial_state: &StateVector, max_support: usize) -> SparseState {
+        let amplitudes: HashMap<_, _> = initial_state.iter()
+            .map(|(state, amplitude)| (state.clone(), amplitude.into()))
+            .collect();
+
+        assert!(
+            amplitudes.len() <= max_support,
+            "The initial state has {} basis states, beyond the limit of {}",
+            amplitudes.len(),
+            max_support,
+        );
+
+        SparseState {
+            size,
+            amplitudes,
+            max_support,
+        }
+    }
+
+    // Returns the maximal number of bytes used by the amplitudes of the state.
+    pub(crate) fn memory_usage(max_support: usize) -> usize {
+        max_support * (std::mem::size_of::<BitString>() + std::mem::size_of::<c128>())
+    }
+
+    // Applies the operations to the state.
+    pub(crate) fn apply(&mut self, operations: &[Operation]) {
+        for operation in operations {
+            let target = operation.target as usize;
+
+            let gate = operation.gate;
+            let gate: [[c128; 2]; 2] = [
+                [gate.u00.into(), gate.u01.into()],
+                [gate.u10.into(), gate.u11.into()],
+            ];
+
+            // Every basis state is sent to the pair of states differing by the target bit
+            let mut result = HashMap::with_capacity(2 * self.amplitudes.len());
+            for (state, &amplitude) in self.amplitudes.iter() {
+                if operation.control.is_some_and(|control| !state.get(control as usize)) {
+                    result.insert(state.clone(), amplitude);
+                    continue;
+                }
+
+                let bit = state.get(target) as usize;
+                for (i, row) in gate.iter().enumerate() {
+                    if row[bit] != c128::ZERO {
+                        let mut state = state.clone();
+                        state.set(target, i == 1);
+
+                        let entry = result.entry(state).or_insert(c128::ZERO);
+                        *entry = *entry + row[bit] * amplitude;
+                    }
+                }
+            }
+
+            result.retain(|_, amplitude: &mut c128| amplitude.norm_sqr() > 1e-24);
+
+            assert!(
+                result.len() <= self.max_support,
+                "The support of the {}-qbits sparse state grew to {} basis states, beyond the limit of {}",
+                self.size,
+                result.len(),
+                self.max_support,
+            );
+
+            self.amplitudes = result;
+        }
+    }
+
+    // Returns the nonzero amplitudes of the state.
+    pub(crate) fn amplitudes(&self) -> impl Iterator<Item = (&BitString, c128)> + '_ {
+        self.amplitudes.iter().map(|(state, amplitude)| (state, *amplitude))
+    }
+
+    // Returns the probability that the qbits #bits[i] are measured in the state of bit #i of `state`.
+    pub(crate) fn probability(&self, bits: &[usize], state: &BitString) -> f64 {
+        let total: f64 = self.amplitudes.values().map(|amplitude| amplitude.norm_sqr()).sum();
+        let probability = self.amplitudes.iter()
+            .filter(|(basis, _)| basis.select(bits) == *state)
+            .fold(0.0, |sum, (_, amplitude)| sum + amplitude.norm_sqr());
+
+        probability / total
+    }
+
+    // Draws `samples` samples and adds them to the `results`, the shot #i being drawn from the
+    // substream #i of the sampling stream of `prng`.
+    pub(crate) fn sample(&self, samples: usize, prng: &Philox, results: &mut HashMap<BitString, usize>) {
+        // Sorted so that the same seed always gives the same results
+        let mut states: Vec<_> = self.amplitudes.iter()
+            .map(|(state, amplitude)| (state, amplitude.norm_sqr()))
+            .collect();
+        states.sort_unstable_by_key(|&(state, _)| state);
+
+        let cumulative: Vec<f64> = states.iter()
+            .scan(0.0, |sum, &(_, p)| {
+                *sum += p;
+                Some(*sum)
+            })
+            .collect();
+        let total = cumulative[cumulative.len() - 1];
+
+        for shot in 0..samples as u64 {
+            let x = prng.substream(Stream::Sampling, shot).next_f64() * total;
+            let i = cumulative.partition_point(|&c| c <= x).min(states.len() - 1);
+            *results.entry(states[i].0.clone()).or_insert(0) += 1;
+        }
+    }
+}
+
+//#################################################################################################
+//
+//                                           Tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitstring::BitOrder;
+    use crate::complex::c64;
+    use crate::gates::Gate;
+
+    fn operation(gate_name: &'static str, gate: Gate, target: Address, control: Option<Address>) -> Operation {
+        Operation {
+            gate_name,
+            gate,
+            target,
+            control,
+        }
+    }
+
+    fn hadamard(target: Address) -> Operation {
+        let sqrt2inv = 2f32.sqrt().recip();
+        operation("H", unsafe { Gate::new_unchecked(sqrt2inv, sqrt2inv, sqrt2inv, -sqrt2inv) }, target, None)
+    }
+
+    fn cnot(control: Address, target: Address) -> Operation {
+        operation("X", unsafe { Gate::new_unchecked(0, 1, 1, 0) }, target, Some(control))
+    }
+
+    fn zeros(size: Address, max_support: usize) -> SparseState {
+        let initial = StateVector::new(size, vec![(BitString::zeros(size as usize), c64::ONE)], false, BitOrder::LittleEndian);
+        SparseState::new(size, &initial, max_support)
+    }
+
+    #[test]
+    fn ghz_distribution() {
+        // Wider than a word, the support stays of 2 basis states
+        let size = 100;
+        let mut state = zeros(size, 2);
+        state.apply(&[hadamard(0)]);
+        state.apply(&(1..size).map(|qbit| cnot(qbit - 1, qbit)).collect::<Vec<_>>());
+
+        let mut ones = BitString::zeros(size as usize);
+        (0..size as usize).for_each(|i| ones.set(i, true));
+
+        assert_eq!(state.amplitudes().count(), 2);
+        assert!((state.probability(&[0, 99], &BitString::from_u64(0b11, 2)) - 0.5).abs() < 1e-12);
+        assert!(state.probability(&[0, 99], &BitString::from_u64(0b01, 2)) < 1e-12);
+
+        let mut results = HashMap::new();
+        state.sample(10000, &Philox::new(0), &mut results);
+
+        assert_eq!(results.len(), 2);
+        let zeros = results[&BitString::zeros(size as usize)];
+        assert_eq!(zeros + results[&ones], 10000);
+        assert!((zeros as f64 / 10000.0 - 0.5).abs() < 0.03, "{} zeros out of 10000", zeros);
+    }
+
+    #[test]
+    fn support_growth() {
+        let mut state = zeros(8, 16);
+
+        // Every Hadamard doubles the support, which shrinks back when they are undone
+        for qbit in 0..4 {
+            state.apply(&[hadamard(qbit)]);
+            assert_eq!(state.amplitudes().count(), 2 << qbit);
+        }
+        for qbit in 0..4 {
+            state.apply(&[hadamard(qbit)]);
+        }
+
+        let amplitudes: Vec<_> = state.amplitudes().collect();
+        assert_eq!(amplitudes.len(), 1);
+        assert_eq!(*amplitudes[0].0, BitString::zeros(8));
+        assert!((amplitudes[0].1.norm_sqr() - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    #[should_panic(expected = "beyond the limit of 8")]
+    fn support_limit() {
+        let mut state = zeros(8, 8);
+        state.apply(&(0..4).map(hadamard).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn controls() {
+        // The control is off, nothing happens
+        let mut state = zeros(3, 4);
+        state.apply(&[cnot(0, 2)]);
+        assert_eq!(state.probability(&[0, 1, 2], &BitString::zeros(3)), 1.0);
+
+        state.apply(&[operation("X", unsafe { Gate::new_unchecked(0, 1, 1, 0) }, 0, None), cnot(0, 2)]);
+        assert!((state.probability(&[0, 1, 2], &BitString::from_u64(0b101, 3)) - 1.0).abs() < 1e-12);
+    }
+}
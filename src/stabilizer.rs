@@ -0,0 +1,534 @@
+use crate::bitstring::BitString;
+use crate::complex::c64;
+use crate::computer::Address;
+use crate::gates::Gate;
+use crate::program::Operation;
+use crate::random::Philox;
+
+//#################################################################################################
+//
+//                                     Clifford compilation
+//
+//#################################################################################################
+
+// The elementary operations the tableau is updated with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum TableauOp {
+    H(usize),
+    S(usize),
+    Cnot(usize, usize),
+}
+
+#[inline]
+fn close(x: c64, y: c64) -> bool {
+    (x - y).norm() < 1e-4
+}
+
+#[inline]
+fn product(a: &Gate, b: &Gate) -> Gate {
+    unsafe {
+        Gate::new_unchecked(
+            a.u00*b.u00 + a.u01*b.u10,
+            a.u00*b.u01 + a.u01*b.u11,
+            a.u10*b.u00 + a.u11*b.u10,
+            a.u10*b.u01 + a.u11*b.u11,
+        )
+    }
+}
+
+#[inline]
+fn entries(gate: &Gate) -> [c64; 4] {
+    [gate.u00, gate.u01, gate.u10, gate.u11]
+}
+
+// Returns the phase λ such that a = λb, if it exists.
+fn relative_phase(a: &Gate, b: &Gate) -> Option<c64> {
+    let (a, b) = (entries(a), entries(b));
+
+    let (i, _) = b.iter()
+        .enumerate()
+        .max_by(|(_, x), (_, y)| x.norm_sqr().partial_cmp(&y.norm_sqr()).unwrap())
+        .unwrap();
+
+    let phase = a[i] / b[i];
+
+    if (phase.norm() - 1.0).abs() < 1e-4 && a.iter().zip(b.iter()).all(|(&x, &y)| close(x, phase * y)) {
+        Some(phase)
+    } else {
+        None
+    }
+}
+
+// Returns the 24 single-qbit Clifford gates, up to a global phase, along with a sequence of
+// H and S implementing each of them, by a breadth-first search in the group they generate.
+fn single_qbit_cliffords() -> Vec<(Gate, Vec<bool>)> {
+    let sqrt2inv = 2f32.sqrt().recip();
+    // `false` stands for H and `true` for S
+    let generators = unsafe {[
+        (false, Gate::new_unchecked(sqrt2inv, sqrt2inv, sqrt2inv, -sqrt2inv)),
+        (true, Gate::new_unchecked(1, 0, 0, c64::I)),
+    ]};
+
+    let mut group = vec![(unsafe { Gate::new_unchecked(1, 0, 0, 1) }, Vec::new())];
+    let mut i = 0;
+
+    while i < group.len() {
+        for (primitive, generator) in generators.iter() {
+            let gate = product(generator, &group[i].0);
+
+            if group.iter().all(|(other, _)| relative_phase(&gate, other).is_none()) {
+                let mut sequence = group[i].1.clone();
+                sequence.push(*primitive);
+                group.push((gate, sequence));
+            }
+        }
+        i += 1;
+    }
+
+    group
+}
+
+// Translates the operations into tableau operations. Returns the index of the first operation
+// that is not a Clifford gate, if any.
+fn compile(operations: &[Operation]) -> Result<Vec<TableauOp>, usize> {
+    let cliffords = single_qbit_cliffords();
+    let paulis = unsafe {[
+        Gate::new_unchecked(1, 0, 0, 1),
+        Gate::new_unchecked(0, 1, 1, 0),
+        Gate::new_unchecked(0, -c64::I, c64::I, 0),
+        Gate::new_unchecked(1, 0, 0, -1),
+    ]};
+
+    let mut ops = Vec::with_capacity(operations.len());
+
+    for (i, operation) in operations.iter().enumerate() {
+        let target = operation.target as usize;
+
+        match operation.control {
+            None => {
+                let (_, sequence) = cliffords.iter()
+                    .find(|(gate, _)| relative_phase(&operation.gate, gate).is_some())
+                    .ok_or(i)?;
+
+                ops.extend(sequence.iter().map(|&s| if s { TableauOp::S(target) } else { TableauOp::H(target) }));
+            },
+            // Controlled gates must be λP with λ in {1, i, -1, -i} and P a Pauli matrix: the phase
+            // is kicked back to the control as S^k
+            Some(control) => {
+                let control = control as usize;
+
+                let (pauli, phase) = paulis.iter()
+                    .enumerate()
+                    .find_map(|(j, pauli)| relative_phase(&operation.gate, pauli).map(|phase| (j, phase)))
+                    .ok_or(i)?;
+
+                let k = [c64::ONE, c64::I, -c64::ONE, -c64::I].iter()
+                    .position(|&x| close(x, phase))
+                    .ok_or(i)?;
+
+                match pauli {
+                    1 => ops.push(TableauOp::Cnot(control, target)),
+                    2 => ops.extend_from_slice(&[
+                        TableauOp::S(target), TableauOp::S(target), TableauOp::S(target),
+                        TableauOp::Cnot(control, target),
+                        TableauOp::S(target),
+                    ]),
+                    3 => ops.extend_from_slice(&[
+                        TableauOp::H(target), TableauOp::Cnot(control, target), TableauOp::H(target),
+                    ]),
+                    _ => (),
+                }
+
+                ops.extend(std::iter::repeat_n(TableauOp::S(control), k));
+            },
+        }
+    }
+
+    Ok(ops)
+}
+
+// Returns the index of the first operation that is not a Clifford gate, if any.
+pub(crate) fn first_non_clifford(operations: &[Operation]) -> Option<usize> {
+    compile(operations).err()
+}
+
+//#################################################################################################
+//
+//                                          Tableau
+//
+//#################################################################################################
+
+// The stabilizer tableau of Aaronson and Gottesman (CHP): rows 0 to n-1 are the destabilizers,
+// rows n to 2n-1 the stabilizers and row 2n is a scratch row.
+//
+// The phases are symbolic: they are affine functions of the random outcomes of measurements,
+// stored as bit vectors, bit #0 being the constant term and bit #j+1 the coefficient of the
+// outcome of the j-th random measurement. This way, the whole distribution of the outcomes is
+// obtained by measuring every qbit once.
+pub(crate) struct Tableau {
+    size: usize,
+    words: usize,
+    phase_words: usize,
+    x: Vec<u64>,
+    z: Vec<u64>,
+    r: Vec<u64>,
+}
+
+impl Tableau {
+    // Creates the tableau of the computational basis state `initial_state`.
+    pub(crate) fn new(size: Address, initial_state: &BitString) -> Tableau {
+        let size = size as usize;
+        let words = size.div_ceil(64);
+        let phase_words = (size + 64) / 64;
+        let rows = 2 * size + 1;
+
+        let mut tableau = Tableau {
+            size,
+            words,
+            phase_words,
+            x: vec![0; rows * words],
+            z: vec![0; rows * words],
+            r: vec![0; rows * phase_words],
+        };
+
+        for i in 0..size {
+            tableau.x[i * words + i / 64] |= 1 << (i % 64);
+            tableau.z[(i + size) * words + i / 64] |= 1 << (i % 64);
+
+            // X|0> = |1>: X flips the sign of the stabilizer Z_i
+            if initial_state.get(i) {
+                tableau.r[(i + size) * phase_words] ^= 1;
+            }
+        }
+
+        tableau
+    }
+
+    // Returns the memory used by the tableau, in bytes.
+    pub(crate) fn memory_usage(&self) -> usize {
+        (self.x.len() + self.z.len() + self.r.len()) * 8
+    }
+
+    // Applies the `operations` to the tableau.
+    //
+    // Panics if one of them is not a Clifford gate.
+    pub(crate) fn apply(&mut self, operations: &[Operation]) {
+        let ops = compile(operations).unwrap_or_else(|i| panic!(
+            "Instruction #{} (gate \"{}\") is not a Clifford gate, it cannot be run by the Stabilizer backend",
+            i,
+            operations[i].gate_name,
+        ));
+
+        for op in ops {
+            match op {
+                TableauOp::H(a) => self.hadamard(a),
+                TableauOp::S(a) => self.phase(a),
+                TableauOp::Cnot(c, t) => self.cnot(c, t),
+            }
+        }
+    }
+
+    #[inline]
+    fn bit(v: &[u64], words: usize, row: usize, col: usize) -> u64 {
+        (v[row * words + col / 64] >> (col % 64)) & 1
+    }
+
+    #[inline]
+    fn toggle(v: &mut [u64], words: usize, row: usize, col: usize, value: u64) {
+        v[row * words + col / 64] ^= value << (col % 64);
+    }
+
+    fn hadamard(&mut self, a: usize) {
+        let w = self.words;
+
+        for i in 0..2 * self.size {
+            let (x, z) = (Tableau::bit(&self.x, w, i, a), Tableau::bit(&self.z, w, i, a));
+
+            self.r[i * self.phase_words] ^= x & z;
+            Tableau::toggle(&mut self.x, w, i, a, x ^ z);
+            Tableau::toggle(&mut self.z, w, i, a, x ^ z);
+        }
+    }
+
+    fn phase(&mut self, a: usize) {
+        let w = self.words;
+
+        for i in 0..2 * self.size {
+            let (x, z) = (Tableau::bit(&self.x, w, i, a), Tableau::bit(&self.z, w, i, a));
+
+            self.r[i * self.phase_words] ^= x & z;
+            Tableau::toggle(&mut self.z, w, i, a, x);
+        }
+    }
+
+    fn cnot(&mut self, c: usize, t: usize) {
+        let w = self.words;
+
+        for i in 0..2 * self.size {
+            let (xc, zc) = (Tableau::bit(&self.x, w, i, c), Tableau::bit(&self.z, w, i, c));
+            let (xt, zt) = (Tableau::bit(&self.x, w, i, t), Tableau::bit(&self.z, w, i, t));
+
+            self.r[i * self.phase_words] ^= xc & zt & (xt ^ zc ^ 1);
+            Tableau::toggle(&mut self.x, w, i, t, xc);
+            Tableau::toggle(&mut self.z, w, i, c, zt);
+        }
+    }
+
+    // Left-multiplies the row #h by the row #i.
+    fn rowsum(&mut self, h: usize, i: usize) {
+        let w = self.words;
+        let mut g = 0i64;
+
+        for k in 0..w {
+            let (x1, z1) = (self.x[i * w + k], self.z[i * w + k]);
+            let (x2, z2) = (self.x[h * w + k], self.z[h * w + k]);
+
+            let plus = (x1 & z1 & z2 & !x2) | (x1 & !z1 & x2 & z2) | (!x1 & z1 & x2 & !z2);
+            let minus = (x1 & z1 & x2 & !z2) | (x1 & !z1 & !x2 & z2) | (!x1 & z1 & x2 & z2);
+
+            g += plus.count_ones() as i64 - minus.count_ones() as i64;
+
+            self.x[h * w + k] ^= x1;
+            self.z[h * w + k] ^= z1;
+        }
+
+        let pw = self.phase_words;
+        for k in 0..pw {
+            self.r[h * pw + k] ^= self.r[i * pw + k];
+        }
+        self.r[h * pw] ^= (g.rem_euclid(4) == 2) as u64;
+    }
+
+    // Measures the qbit #a and returns the outcome as an affine function of the random outcomes.
+    fn measure(&mut self, a: usize, free: &mut usize) -> Box<[u64]> {
+        let (n, w, pw) = (self.size, self.words, self.phase_words);
+
+        let p = (n..2 * n).find(|&p| Tableau::bit(&self.x, w, p, a) == 1);
+
+        match p {
+            // Random outcome
+            Some(p) => {
+                for i in 0..2 * n {
+                    if i != p && Tableau::bit(&self.x, w, i, a) == 1 {
+                        self.rowsum(i, p);
+                    }
+                }
+
+                self.x.copy_within(p * w..(p + 1) * w, (p - n) * w);
+                self.z.copy_within(p * w..(p + 1) * w, (p - n) * w);
+                self.r.copy_within(p * pw..(p + 1) * pw, (p - n) * pw);
+
+                for k in 0..w {
+                    self.x[p * w + k] = 0;
+                    self.z[p * w + k] = 0;
+                }
+                for k in 0..pw {
+                    self.r[p * pw + k] = 0;
+                }
+                Tableau::toggle(&mut self.z, w, p, a, 1);
+
+                *free += 1;
+                Tableau::toggle(&mut self.r, pw, p, *free, 1);
+
+                self.r[p * pw..(p + 1) * pw].into()
+            },
+            // Deterministic outcome
+            None => {
+                let scratch = 2 * n;
+
+                for k in 0..w {
+                    self.x[scratch * w + k] = 0;
+                    self.z[scratch * w + k] = 0;
+                }
+                for k in 0..pw {
+                    self.r[scratch * pw + k] = 0;
+                }
+
+                for i in 0..n {
+                    if Tableau::bit(&self.x, w, i, a) == 1 {
+                        self.rowsum(scratch, i + n);
+                    }
+                }
+
+                self.r[scratch * pw..(scratch + 1) * pw].into()
+            },
+        }
+    }
+
+    // Measures every qbit and returns the distribution of the outcomes.
+    pub(crate) fn measure_all(mut self) -> AffineDistribution {
+        let mut free = 0;
+        let outcomes = (0..self.size)
+            .map(|a| self.measure(a, &mut free))
+            .collect();
+
+        AffineDistribution {
+            outcomes,
+            free,
+        }
+    }
+}
+
+//#################################################################################################
+//
+//                                   Affine distribution
+//
+//#################################################################################################
+
+// The distribution of the outcomes of the measurement of a stabilizer state: the uniform
+// distribution over an affine subspace.
+pub(crate) struct AffineDistribution {
+    outcomes: Box<[Box<[u64]>]>,
+    free: usize,
+}
+
+impl AffineDistribution {
+    // Draws one sample of the distribution.
+    pub(crate) fn sample(&self, prng: &mut Philox) -> BitString {
+        let words = (self.free + 64) / 64;
+        let mut variables = vec![0u64; words];
+
+        variables[0] = 1;
+        for j in 1..=self.free {
+            variables[j / 64] |= ((prng.next_u32() & 1) as u64) << (j % 64);
+        }
+
+        let mut state = BitString::zeros(self.outcomes.len());
+        for (a, outcome) in self.outcomes.iter().enumerate() {
+            let parity = outcome.iter()
+                .zip(variables.iter())
+                .fold(0, |acc, (x, y)| acc ^ (x & y).count_ones()) & 1;
+            state.set(a, parity == 1);
+        }
+        state
+    }
+
+    // Returns the probability that the qbits #bits[i] are measured in the state of bit #i of
+    // `state`: 2^-rank if the linear system of their outcomes over the random ones has a solution,
+    // rank being it's rank, and 0 otherwise.
+    pub(crate) fn probability(&self, bits: &[usize], state: &BitString) -> f64 {
+        // The constant terms are replaced by their difference with the expected outcomes
+        let mut rows: Vec<Box<[u64]>> = bits.iter()
+            .enumerate()
+            .map(|(i, &a)| {
+                let mut row = self.outcomes[a].clone();
+                row[0] ^= state.get(i) as u64;
+                row
+            })
+            .collect();
+
+        // Gauss-Jordan elimination over the random outcomes
+        let mut rank = 0;
+        for j in 1..=self.free {
+            let (word, bit) = (j / 64, 1 << (j % 64));
+
+            if let Some(pivot) = (rank..rows.len()).find(|&r| rows[r][word] & bit != 0) {
+                rows.swap(rank, pivot);
+                let pivot = rows[rank].clone();
+
+                for (r, row) in rows.iter_mut().enumerate() {
+                    if r != rank && row[word] & bit != 0 {
+                        for (x, y) in row.iter_mut().zip(pivot.iter()) {
+                            *x ^= y;
+                        }
+                    }
+                }
+
+                rank += 1;
+            }
+        }
+
+        // The remaining rows don't depend on the random outcomes
+        if rows[rank..].iter().any(|row| row[0] & 1 != 0) {
+            0.0
+        } else {
+            0.5f64.powi(rank as i32)
+        }
+    }
+}
+
+//#################################################################################################
+//
+//                                           Tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operation(gate_name: &'static str, gate: Gate, target: Address, control: Option<Address>) -> Operation {
+        Operation {
+            gate_name,
+            gate,
+            target,
+            control,
+        }
+    }
+
+    // The operations preparing the GHZ state (|00...0> + |11...1>)/√2 of `size` qbits.
+    fn ghz(size: Address) -> Vec<Operation> {
+        let sqrt2inv = 2f32.sqrt().recip();
+        let (h, x) = unsafe {
+            (Gate::new_unchecked(sqrt2inv, sqrt2inv, sqrt2inv, -sqrt2inv), Gate::new_unchecked(0, 1, 1, 0))
+        };
+
+        let mut operations = vec![operation("H", h, 0, None)];
+        operations.extend((1..size).map(|qbit| operation("X", x, qbit, Some(qbit - 1))));
+        operations
+    }
+
+    #[test]
+    fn ghz_distribution() {
+        // Wider than a word, to cover the multi-word rows
+        for &size in [3, 70].iter() {
+            let n = size as usize;
+            let mut tableau = Tableau::new(size, &BitString::zeros(n));
+            tableau.apply(&ghz(size));
+            let distribution = tableau.measure_all();
+
+            let bits: Vec<usize> = (0..n).collect();
+            let mut ones = BitString::zeros(n);
+            (0..n).for_each(|i| ones.set(i, true));
+            let mut other = BitString::zeros(n);
+            other.set(n - 1, true);
+
+            assert_eq!(distribution.probability(&bits, &BitString::zeros(n)), 0.5);
+            assert_eq!(distribution.probability(&bits, &ones), 0.5);
+            assert_eq!(distribution.probability(&bits, &other), 0.0);
+
+            // Every qbit alone is uniform, and any two are equal
+            assert_eq!(distribution.probability(&[n / 2], &BitString::from_u64(1, 1)), 0.5);
+            assert_eq!(distribution.probability(&[0, n - 1], &BitString::from_u64(0b01, 2)), 0.0);
+
+            let mut prng = Philox::new(0);
+            let samples = 10000;
+            let zeros = (0..samples)
+                .map(|_| distribution.sample(&mut prng))
+                .filter(|state| {
+                    assert!(*state == ones || state.count_ones() == 0, "Sampled {:?} from a GHZ state", state);
+                    state.count_ones() == 0
+                })
+                .count();
+            assert!((zeros as f64 / samples as f64 - 0.5).abs() < 0.03, "{} zeros out of {}", zeros, samples);
+        }
+    }
+
+    #[test]
+    fn basis_state() {
+        let state = BitString::from_u64(0b1011, 4);
+        let distribution = Tableau::new(4, &state).measure_all();
+
+        assert_eq!(distribution.probability(&[0, 1, 2, 3], &state), 1.0);
+        assert_eq!(distribution.sample(&mut Philox::new(0)), state);
+    }
+
+    #[test]
+    fn non_clifford_detection() {
+        let mut operations = ghz(3);
+        assert_eq!(first_non_clifford(&operations), None);
+
+        operations.insert(2, operation("T", Gate::phase_shift(std::f32::consts::FRAC_PI_4), 1, None));
+        assert_eq!(first_non_clifford(&operations), Some(2));
+    }
+}
@@ -0,0 +1,186 @@
+use std::fmt;
+
+use crate::bitstring::{BitOrder, BitString};
+use crate::complex::c64;
+use crate::computer::Address;
+
+//#################################################################################################
+//
+//                                       State Vector
+//
+//#################################################################################################
+
+/// Represents the pure state `|ψ>` of `size` qbits by it's nonzero amplitudes. It can be obtained
+/// from a computer with `Computer::state_vector` and used as the initial state of another program.
+/// 
+/// It is displayed as a sum of kets written in the bit order of the computer it comes from, which
+/// can be parsed back by `Computer::new_program`.
+#[derive(Clone, Debug)]
+pub struct StateVector {
+    size: Address,
+    // Sorted by basis state
+    amplitudes: Box<[(BitString, c64)]>,
+    bit_order: BitOrder,
+}
+
+impl StateVector {
+    // Creates the state from it's amplitudes, summing those of the same basis state and dropping
+    // the null ones. It's kets are written in `bit_order`.
+    //
+    // Panics if the state has a null norm. If `normalize` is false, panics if the state is not
+    // normalized.
+    pub(crate) fn new(
+        size: Address,
+        amplitudes: Vec<(BitString, c64)>,
+        normalize: bool,
+        bit_order: BitOrder,
+    ) -> StateVector {
+        let mut amplitudes = amplitudes;
+        amplitudes.sort_by(|(a, _), (b, _)| a.cmp(b));
+        amplitudes.dedup_by(|(state, amplitude), (previous, sum)| {
+            let same = state == previous;
+            if same {
+                *sum = *sum + *amplitude;
+            }
+            same
+        });
+        amplitudes.retain(|(_, amplitude)| *amplitude != c64::ZERO);
+
+        let norm: f64 = amplitudes.iter()
+            .map(|(_, amplitude)| amplitude.norm_sqr() as f64)
+            .sum();
+
+        assert!(
+            norm > 1e-12,
+            "The state has a null norm",
+        );
+
+        if normalize {
+            let scale = c64::from(norm.sqrt().recip());
+            for (_, amplitude) in amplitudes.iter_mut() {
+                *amplitude = *amplitude * scale;
+            }
+        } else {
+            assert!(
+                (norm - 1.0).abs() < 1e-4,
+                "The state is not normalized, it's squared norm is {}",
+                norm,
+            );
+        }
+
+        StateVector {
+            size,
+            amplitudes: amplitudes.into(),
+            bit_order,
+        }
+    }
+
+    // Returns the same state, written in `bit_order`.
+    pub(crate) fn with_bit_order(&self, bit_order: BitOrder) -> StateVector {
+        StateVector {
+            bit_order,
+            ..self.clone()
+        }
+    }
+
+    // Returns the basis state and it's amplitude if the state is a basis state, up to a global
+    // phase.
+    pub(crate) fn basis_state(&self) -> Option<(&BitString, c64)> {
+        match &*self.amplitudes {
+            [(state, amplitude)] => Some((state, *amplitude)),
+            _ => None,
+        }
+    }
+
+    // Returns the `2^size` amplitudes of the state, the index of the amplitude of the basis state
+    // |s> being s.
+    pub(crate) fn dense(&self) -> Vec<c64> {
+        let mut result = vec![c64::ZERO; 1 << self.size];
+        for (state, amplitude) in self.amplitudes.iter() {
+            result[state.to_u64().unwrap() as usize] = *amplitude;
+        }
+        result
+    }
+
+    /// Returns the number of qbits of the state.
+    pub fn size(&self) -> Address {
+        self.size
+    }
+
+    /// Returns the amplitude `<state|ψ>`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `state` is not `size` bits long.
+    pub fn amplitude(&self, state: &BitString) -> c64 {
+        assert!(
+            state.len() == self.size as usize,
+            "The state |{}> is not {} bits long",
+            state,
+            self.size,
+        );
+
+        self.amplitudes.binary_search_by(|(s, _)| s.cmp(state))
+            .map_or(c64::ZERO, |i| self.amplitudes[i].1)
+    }
+
+    /// Returns the probability `|<state|ψ>|²` of measuring `state`.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `state` is not `size` bits long.
+    pub fn probability(&self, state: &BitString) -> f64 {
+        self.amplitude(state).norm_sqr() as f64
+    }
+
+    /// Returns an iterator over the basis states of nonzero amplitude and their amplitudes, sorted
+    /// by basis state.
+    pub fn iter(&self) -> impl Iterator<Item = (&BitString, c64)> + '_ {
+        self.amplitudes.iter().map(|(state, amplitude)| (state, *amplitude))
+    }
+}
+
+// Formats the state as a sum of kets, such as `0.6|00> + 0.8i|11>`.
+impl fmt::Display for StateVector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some((state, amplitude)) = self.basis_state() {
+            if amplitude.approx_eq(c64::ONE) {
+                return write!(f, "|{}>", state.display(self.bit_order));
+            }
+        }
+
+        // Sorted as written, the digits depending on the bit order
+        let mut terms: Vec<_> = self.amplitudes.iter()
+            .map(|(state, amplitude)| (state.display(self.bit_order).to_string(), *amplitude))
+            .collect();
+        terms.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        for (i, (state, mut amplitude)) in terms.into_iter().enumerate() {
+            if i != 0 {
+                // Real or imaginary amplitudes are written with their sign as the separator
+                let negative = match (amplitude.re(), amplitude.im()) {
+                    (re, 0.0) => re < 0.0,
+                    (0.0, im) => im < 0.0,
+                    _ => false,
+                };
+
+                if negative {
+                    amplitude = c64::ZERO - amplitude;
+                    write!(f, " - ")?;
+                } else {
+                    write!(f, " + ")?;
+                }
+            }
+
+            match (amplitude.re(), amplitude.im()) {
+                (re, 0.0) => write!(f, "{}", re)?,
+                (0.0, im) => write!(f, "{}i", im)?,
+                (re, im) => write!(f, "({}{:+}i)", re, im)?,
+            }
+
+            write!(f, "|{}>", state)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use crate::bitstring::BitString;
+
+//#################################################################################################
+//
+//                                     Special functions
+//
+//#################################################################################################
+
+const MAX_ITERATIONS: usize = 1000;
+const EPSILON: f64 = 1e-15;
+
+// Returns ln Γ(x), with the Lanczos approximation (g = 7, n = 9).
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let t = x + 7.5;
+    let sum = COEFFICIENTS[1..].iter()
+        .enumerate()
+        .fold(COEFFICIENTS[0], |sum, (i, c)| sum + c / (x + (i + 1) as f64));
+
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+// Returns the regularized upper incomplete gamma function Q(a, x) = Γ(a, x) / Γ(a), with it's
+// series for x < a + 1 and it's continued fraction otherwise.
+fn gamma_q(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+
+    let front = a * x.ln() - x - ln_gamma(a);
+
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..MAX_ITERATIONS {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+
+        1.0 - sum * front.exp()
+    } else {
+        // Modified Lentz's method
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for n in 1..MAX_ITERATIONS {
+            let an = -(n as f64) * (n as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < EPSILON {
+                break;
+            }
+        }
+
+        front.exp() * h
+    }
+}
+
+// Returns the continued fraction of the regularized incomplete beta function, with the modified
+// Lentz's method.
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    let tiny = 1e-300;
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < tiny {
+        d = tiny;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+
+    for m in 1..MAX_ITERATIONS {
+        let m = m as f64;
+
+        for numerator in [
+            m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0)),
+        ] {
+            d = 1.0 + numerator * d;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = 1.0 + numerator / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            h *= d * c;
+        }
+
+        if (d * c - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+
+    h
+}
+
+// Returns the regularized incomplete beta function I_x(a, b).
+fn beta_regularized(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+
+    // The continued fraction converges quickly on this side of the mean
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+// Returns x in [low, high] such that the increasing function `cdf` reaches `p` at x, by bisection.
+fn quantile<F>(p: f64, mut low: f64, mut high: f64, cdf: F) -> f64
+where
+    F: Fn(f64) -> f64,
+{
+    for _ in 0..200 {
+        let middle = 0.5 * (low + high);
+        if cdf(middle) < p {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+
+    0.5 * (low + high)
+}
+
+// Returns the cumulative distribution function of the standard normal distribution.
+fn normal_cdf(x: f64) -> f64 {
+    // erfc(y) = Q(1/2, y²)
+    let tail = 0.5 * gamma_q(0.5, 0.5 * x * x);
+    if x < 0.0 {
+        tail
+    } else {
+        1.0 - tail
+    }
+}
+
+// Returns the quantile of order `p` of the standard normal distribution.
+fn normal_quantile(p: f64) -> f64 {
+    quantile(p, -40.0, 40.0, normal_cdf)
+}
+
+// Returns the quantile of order `p` of the beta distribution of parameters `a` and `b`.
+fn beta_quantile(p: f64, a: f64, b: f64) -> f64 {
+    quantile(p, 0.0, 1.0, |x| beta_regularized(a, b, x))
+}
+
+// Returns the probability that a chi-squared variable with `degrees_of_freedom` exceeds `x`.
+pub(crate) fn chi_squared_survival(x: f64, degrees_of_freedom: usize) -> f64 {
+    if x.is_infinite() {
+        return 0.0;
+    }
+
+    gamma_q(0.5 * degrees_of_freedom as f64, 0.5 * x)
+}
+
+//#################################################################################################
+//
+//                                    Confidence intervals
+//
+//#################################################################################################
+
+/// The method used to compute the confidence interval of the probability of an outcome, from the
+/// number of times it was measured.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Interval {
+    /// The Wilson score interval, which is accurate even for rare outcomes and few samples.
+    Wilson,
+    /// The exact Clopper-Pearson interval, built from quantiles of the beta distribution. It is
+    /// conservative: it's coverage is at least the requested confidence.
+    ClopperPearson,
+}
+
+impl Interval {
+    // Returns the confidence interval of the probability of an outcome measured `count` times out
+    // of `samples`.
+    pub(crate) fn bounds(self, count: usize, samples: usize, confidence: f64) -> (f64, f64) {
+        let (k, n) = (count as f64, samples as f64);
+        let alpha = 1.0 - confidence;
+
+        match self {
+            Interval::Wilson => {
+                let z = normal_quantile(1.0 - 0.5 * alpha);
+                let p = k / n;
+                let denominator = 1.0 + z * z / n;
+                let center = (p + z * z / (2.0 * n)) / denominator;
+                let half = z / denominator * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt();
+
+                ((center - half).max(0.0), (center + half).min(1.0))
+            },
+            Interval::ClopperPearson => {
+                let lower = if count == 0 {
+                    0.0
+                } else {
+                    beta_quantile(0.5 * alpha, k, n - k + 1.0)
+                };
+                let upper = if count == samples {
+                    1.0
+                } else {
+                    beta_quantile(1.0 - 0.5 * alpha, k + 1.0, n - k)
+                };
+
+                (lower, upper)
+            },
+        }
+    }
+}
+
+//#################################################################################################
+//
+//                                    Goodness of fit test
+//
+//#################################################################################################
+
+/// The result of Pearson's chi-squared goodness of fit test of measurements against an expected
+/// distribution, see `Measurements::chi_squared`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ChiSquared {
+    /// The test statistic `Σ (observed - expected)² / expected`, infinite if a state of null
+    /// expected probability was measured.
+    pub statistic: f64,
+    /// The number of degrees of freedom: the number of states of nonzero expected probability,
+    /// minus one.
+    pub degrees_of_freedom: usize,
+    /// The probability of obtaining a statistic at least as large if the measurements follow the
+    /// expected distribution. Small values reject the expected distribution.
+    pub p_value: f64,
+}
+
+//#################################################################################################
+//
+//                                         Distances
+//
+//#################################################################################################
+
+// Returns the total variation distance ½ Σ |p(s) - q(s)| between two distributions.
+pub(crate) fn total_variation(p: &HashMap<BitString, f64>, q: &HashMap<BitString, f64>) -> f64 {
+    let only_q: f64 = q.iter()
+        .filter(|(state, _)| !p.contains_key(state))
+        .map(|(_, y)| y.abs())
+        .sum();
+
+    let both: f64 = p.iter()
+        .map(|(state, x)| (x - q.get(state).unwrap_or(&0.0)).abs())
+        .sum();
+
+    0.5 * (both + only_q)
+}
+
+// Returns the Hellinger distance √(1 - Σ √(p(s) q(s))) between two distributions.
+pub(crate) fn hellinger(p: &HashMap<BitString, f64>, q: &HashMap<BitString, f64>) -> f64 {
+    let overlap: f64 = p.iter()
+        .filter_map(|(state, x)| q.get(state).map(|y| (x * y).max(0.0).sqrt()))
+        .sum();
+
+    (1.0 - overlap).max(0.0).sqrt()
+}
+
+//#################################################################################################
+//
+//                                           Tests
+//
+//#################################################################################################
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(x: f64, expected: f64, tolerance: f64) {
+        assert!((x - expected).abs() < tolerance, "{} differs from {}", x, expected);
+    }
+
+    #[test]
+    fn special_functions() {
+        assert_close(ln_gamma(5.0), 24f64.ln(), 1e-12);
+        assert_close(ln_gamma(0.5), 0.5723649429247004, 1e-12);
+        assert_close(ln_gamma(0.1), 2.2527126517342055, 1e-12);
+        assert_close(ln_gamma(10.3), 13.48203678613836, 1e-12);
+
+        assert_close(normal_cdf(1.0), 0.8413447460685429, 1e-12);
+        assert_close(normal_cdf(-1.0), 0.15865525393145707, 1e-12);
+        assert_close(normal_quantile(0.975), 1.959963984540054, 1e-9);
+
+        // I_x(1, 1) = x and I_x(a, 1) = x^a
+        assert_close(beta_regularized(1.0, 1.0, 0.3), 0.3, 1e-12);
+        assert_close(beta_regularized(3.0, 1.0, 0.5), 0.125, 1e-12);
+        assert_close(beta_regularized(2.5, 4.0, 0.3) + beta_regularized(4.0, 2.5, 0.7), 1.0, 1e-12);
+    }
+
+    #[test]
+    fn chi_squared_known_values() {
+        assert_close(chi_squared_survival(3.841458820694124, 1), 0.05, 1e-12);
+        assert_close(chi_squared_survival(4.0, 1), 0.045500263896358396, 1e-12);
+        assert_close(chi_squared_survival(4.0, 2), (-2f64).exp(), 1e-12);
+        assert_close(chi_squared_survival(18.307038053275146, 10), 0.05, 1e-12);
+        assert_eq!(chi_squared_survival(0.0, 3), 1.0);
+        assert_eq!(chi_squared_survival(f64::INFINITY, 3), 0.0);
+    }
+
+    #[test]
+    fn wilson_known_values() {
+        let (low, high) = Interval::Wilson.bounds(10, 100, 0.95);
+        assert_close(low, 0.0552291370606751, 1e-9);
+        assert_close(high, 0.17436566150491345, 1e-9);
+
+        // Never empty, even without any success
+        let (low, high) = Interval::Wilson.bounds(0, 10, 0.95);
+        assert_eq!(low, 0.0);
+        assert!(high > 0.2);
+    }
+
+    #[test]
+    fn clopper_pearson_known_values() {
+        let (low, high) = Interval::ClopperPearson.bounds(10, 100, 0.95);
+        assert_close(low, 0.04900469, 1e-8);
+        assert_close(high, 0.17622260, 1e-8);
+
+        let (low, high) = Interval::ClopperPearson.bounds(5, 10, 0.95);
+        assert_close(low, 0.1870860, 1e-7);
+        assert_close(high, 0.8129140, 1e-7);
+
+        // The bounds of the extreme counts are closed forms
+        let (low, high) = Interval::ClopperPearson.bounds(0, 10, 0.95);
+        assert_eq!(low, 0.0);
+        assert_close(high, 1.0 - 0.025f64.powf(0.1), 1e-12);
+
+        let (low, high) = Interval::ClopperPearson.bounds(10, 10, 0.95);
+        assert_close(low, 0.025f64.powf(0.1), 1e-12);
+        assert_eq!(high, 1.0);
+    }
+
+    #[test]
+    fn distances() {
+        let state = |value| BitString::from_u64(value, 2);
+        let p: HashMap<BitString, f64> = vec![(state(0), 0.5), (state(1), 0.5)].into_iter().collect();
+        let q: HashMap<BitString, f64> = vec![(state(1), 0.5), (state(2), 0.5)].into_iter().collect();
+
+        assert_close(total_variation(&p, &p), 0.0, 1e-12);
+        assert_close(total_variation(&p, &q), 0.5, 1e-12);
+        assert_close(hellinger(&p, &p), 0.0, 1e-12);
+        assert_close(hellinger(&p, &q), 0.5f64.sqrt(), 1e-12);
+    }
+}